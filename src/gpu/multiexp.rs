@@ -5,13 +5,15 @@ use super::utils;
 use crate::bls::Engine;
 use crate::multicore::Worker;
 use crate::multiexp::{multiexp as cpu_multiexp, multiexp_with_cpu, FullDensity};
-use ff::{PrimeField, ScalarEngine};
+use ff::{Field, PrimeField, ScalarEngine};
 use groupy::{CurveAffine, CurveProjective};
 use log::{error, info};
 use rust_gpu_tools::*;
-use std::any::TypeId;
-use std::sync::Arc;
-// use std::time::Instant;
+use std::any::{Any, TypeId};
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, AtomicU8, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
 
 use std::sync::mpsc;
 extern crate scoped_threadpool;
@@ -19,7 +21,6 @@ use scoped_threadpool::Pool;
 use crate::SynthesisError;
 
 // const MAX_WINDOW_SIZE: usize = 11;
-const LOCAL_WORK_SIZE: usize = 256;
 // const MEMORY_PADDING: f64 = 0.1f64; // Let 20% of GPU memory be free
 
 pub fn get_cpu_utilization() -> f64 {
@@ -37,23 +38,378 @@ pub fn get_cpu_utilization() -> f64 {
         .min(1f64)
 }
 
+/// Number of usable GPUs detected on this machine, for callers that want to
+/// fan work out across every device instead of going through a single
+/// `MultiexpKernel`/`LockedMultiexpKernel` (which already shares one call's
+/// work across devices, but can't help a caller that wants to keep a
+/// *separate* kernel resident per device across many calls). Falls back to
+/// `1` so callers can always divide by this value, even with no GPU present.
+pub fn device_count() -> usize {
+    opencl::Device::all()
+        .map(|devices| devices.len())
+        .unwrap_or(0)
+        .max(1)
+}
+
+/// The actual `opencl::Device` list backing `device_count`, for callers that
+/// spin up one worker per GPU and want each worker to bind its own
+/// `SingleMultiexpKernel` to exactly one of these devices (instead of every
+/// worker pulling in a `MultiexpKernel`/`LockedMultiexpKernel` that claims
+/// *all* devices under the single global `locks::GPULock`).
+pub fn devices() -> GPUResult<Vec<opencl::Device>> {
+    opencl::Device::all()
+}
+
+/// Multi-level replacement for the old binary `priority: bool` gate on GPU
+/// access. `locks::PriorityLock::should_break` (checked once, at the start
+/// of a `multiexp`/`multiexp_pipelined` call) still works exactly as it did
+/// and is left untouched here, since `locks` isn't owned by this module.
+/// `PriorityHandle` is layered on top of it: a job registers at a numeric
+/// level for as long as it's in flight, and `multiexp_pipelined` samples
+/// `should_yield` between *chunks* (not just once per call), so a
+/// long-running low-priority batch can release the device mid-multiexp the
+/// moment a higher-priority job shows up, instead of only at its next call.
+pub type PriorityLevel = u8;
+
+/// Default level for ordinary batch/background proving.
+pub const BACKGROUND_PRIORITY: PriorityLevel = 0;
+/// Level used by the existing `priority: bool` call sites when `true`,
+/// so a plain `PriorityHandle::from_bool(true)` always outranks every
+/// background job.
+pub const INTERACTIVE_PRIORITY: PriorityLevel = 255;
+
+static NEXT_PRIORITY_ID: AtomicU64 = AtomicU64::new(1);
+static PRIORITY_WAITING: Mutex<Vec<(u64, PriorityLevel)>> = Mutex::new(Vec::new());
+
+fn priority_register(level: PriorityLevel) -> u64 {
+    let id = NEXT_PRIORITY_ID.fetch_add(1, Ordering::Relaxed);
+    PRIORITY_WAITING.lock().unwrap().push((id, level));
+    id
+}
+
+fn priority_set(id: u64, level: PriorityLevel) {
+    let mut waiting = PRIORITY_WAITING.lock().unwrap();
+    if let Some(entry) = waiting.iter_mut().find(|(i, _)| *i == id) {
+        entry.1 = level;
+    }
+}
+
+fn priority_unregister(id: u64) {
+    PRIORITY_WAITING.lock().unwrap().retain(|&(i, _)| i != id);
+}
+
+/// Is some *other* registered job currently waiting at a strictly higher
+/// level than `mine`?
+fn priority_should_yield(excluding: u64, mine: PriorityLevel) -> bool {
+    PRIORITY_WAITING
+        .lock()
+        .unwrap()
+        .iter()
+        .any(|&(id, level)| id != excluding && level > mine)
+}
+
+struct PriorityHandleInner {
+    id: u64,
+    level: AtomicU8,
+}
+
+impl Drop for PriorityHandleInner {
+    fn drop(&mut self) {
+        priority_unregister(self.id);
+    }
+}
+
+/// Caller-held handle for one in-flight proof's priority. Cloning shares the
+/// same registration, so a kernel can hold a clone to check `should_yield`
+/// while the caller holds another to `raise`/`cancel` it from elsewhere (for
+/// example a server bumping a background proof's priority, or dropping it
+/// back down, while that proof's multiexp is still running). Registration
+/// ends when the last clone is dropped.
+#[derive(Clone)]
+pub struct PriorityHandle(Arc<PriorityHandleInner>);
+
+impl PriorityHandle {
+    pub fn new(level: PriorityLevel) -> Self {
+        let id = priority_register(level);
+        PriorityHandle(Arc::new(PriorityHandleInner {
+            id,
+            level: AtomicU8::new(level),
+        }))
+    }
+
+    /// Bridge from the existing binary `priority: bool` flag, so every
+    /// existing call site keeps working unchanged while still taking part
+    /// in preemption against any numeric-priority caller.
+    pub fn from_bool(priority: bool) -> Self {
+        Self::new(if priority {
+            INTERACTIVE_PRIORITY
+        } else {
+            BACKGROUND_PRIORITY
+        })
+    }
+
+    /// Raise (or lower) this job's priority while it's in flight.
+    pub fn raise(&self, level: PriorityLevel) {
+        self.0.level.store(level, Ordering::SeqCst);
+        priority_set(self.0.id, level);
+    }
+
+    /// Drop this job back to background priority, e.g. once the
+    /// latency-sensitive work it was raised for has completed.
+    pub fn cancel(&self) {
+        self.raise(BACKGROUND_PRIORITY);
+    }
+
+    pub fn level(&self) -> PriorityLevel {
+        self.0.level.load(Ordering::SeqCst)
+    }
+
+    /// Is some other registered job currently waiting at a higher level?
+    pub fn should_yield(&self) -> bool {
+        priority_should_yield(self.0.id, self.level())
+    }
+}
+
+impl Default for PriorityHandle {
+    fn default() -> Self {
+        Self::new(BACKGROUND_PRIORITY)
+    }
+}
+
+/// Lock-free exponential moving average of one execution path's measured
+/// throughput (elements/sec), stored as the bits of an `f64` so it can be
+/// updated from inside a `rayon::scope` closure without a `Mutex`. A zero
+/// bit pattern doubles as "no sample yet" since a real throughput is always
+/// positive.
+struct ThroughputEma {
+    bits: AtomicU64,
+}
+
+impl ThroughputEma {
+    const fn new() -> Self {
+        Self {
+            bits: AtomicU64::new(0),
+        }
+    }
+
+    fn get(&self) -> Option<f64> {
+        match self.bits.load(Ordering::Relaxed) {
+            0 => None,
+            bits => Some(f64::from_bits(bits)),
+        }
+    }
+
+    /// Fold a freshly observed elements/sec sample into the running
+    /// average. A 0.3 weight on each new sample is enough to converge
+    /// within a handful of calls without one unusually slow or fast call
+    /// swinging the split wildly.
+    fn update(&self, sample: f64) {
+        const ALPHA: f64 = 0.3;
+        let next = match self.get() {
+            Some(prev) => prev + ALPHA * (sample - prev),
+            None => sample,
+        };
+        self.bits.store(next.to_bits(), Ordering::Relaxed);
+    }
+}
+
+static GPU_THROUGHPUT: ThroughputEma = ThroughputEma::new();
+static CPU_THROUGHPUT: ThroughputEma = ThroughputEma::new();
+
+/// Fraction of elements routed to the CPU path before either `ThroughputEma`
+/// has a sample. Has to be non-zero: `CPU_THROUGHPUT` only ever gets a
+/// sample when `cpu_n > 0`, so a `0.0` seed would keep the CPU path
+/// permanently disabled and the EMA would never bootstrap.
+///
+/// Seeded from `BELLMAN_CPU_UTILIZATION` when it's set, so a caller who's
+/// already tuned that knob gets a sane starting split instead of the EMA
+/// bootstrapping from a generic default; falls back to `0.2` only when the
+/// var is unset.
+fn initial_cpu_fraction() -> f64 {
+    std::env::var("BELLMAN_CPU_UTILIZATION")
+        .ok()
+        .and_then(|v| v.parse::<f64>().ok())
+        .map(|v| v.max(0f64).min(1f64))
+        .unwrap_or(0.2)
+}
+
+/// Fraction of a multiexp's elements that should be routed to
+/// `only_cpu_multiexp` for this call.
+///
+/// The split is derived from an EMA of each path's measured elements/sec
+/// from previous calls, so the CPU and GPU finish their halves at roughly
+/// the same time regardless of the machine's actual relative speed. Until
+/// both paths have a sample, the split comes from `initial_cpu_fraction`
+/// (`BELLMAN_CPU_UTILIZATION` when set), so the CPU path actually runs at
+/// least once and the EMA has something to converge from. An explicit
+/// `BELLMAN_CPU_UTILIZATION=0` therefore keeps the CPU path off.
+fn adaptive_cpu_fraction() -> f64 {
+    match (CPU_THROUGHPUT.get(), GPU_THROUGHPUT.get()) {
+        (Some(cpu), Some(gpu)) => (cpu / (cpu + gpu)).max(0f64).min(1f64),
+        _ => initial_cpu_fraction(),
+    }
+}
+
+/// Pool of already-allocated device buffers, keyed by element type and
+/// capacity, so the hot multiexp loop doesn't pay for a `create_buffer`
+/// (and the driver-side alloc/free that comes with it) on every chunk. A
+/// `take` either hands back a free buffer of that exact capacity or returns
+/// `None` on a miss, in which case the caller allocates and later `put`s it
+/// back for next time.
+#[derive(Default)]
+struct BufferPool {
+    free: HashMap<(TypeId, usize), Vec<Box<dyn Any + Send>>>,
+}
+
+impl BufferPool {
+    fn take<T: 'static>(&mut self, capacity: usize) -> Option<opencl::Buffer<T>> {
+        let list = self.free.get_mut(&(TypeId::of::<T>(), capacity))?;
+        let boxed = list.pop()?;
+        Some(*boxed.downcast::<opencl::Buffer<T>>().expect("pool key/type mismatch"))
+    }
+
+    fn put<T: 'static + Send>(&mut self, capacity: usize, buffer: opencl::Buffer<T>) {
+        self.free
+            .entry((TypeId::of::<T>(), capacity))
+            .or_insert_with(Vec::new)
+            .push(Box::new(buffer));
+    }
+}
+
+/// Result of the one-time per-device `multiexp_pipelined` chunk-size sweep,
+/// cached to disk so later processes don't have to pay for it again.
+#[derive(Clone, Copy)]
+struct DeviceTuningProfile {
+    chunk_size: usize,
+}
+
+/// Directory the tuning cache is stored under. Overridable via
+/// `BELLMAN_GPU_TUNING_CACHE_DIR`, matching the `BELLMAN_*` env var
+/// convention used elsewhere in this module (e.g. `BELLMAN_CPU_UTILIZATION`).
+fn tuning_cache_dir() -> String {
+    std::env::var("BELLMAN_GPU_TUNING_CACHE_DIR").unwrap_or_else(|_| {
+        std::env::var("HOME")
+            .map(|home| format!("{}/.bellperson-gpu-tuning", home))
+            .unwrap_or_else(|_| ".bellperson-gpu-tuning".to_string())
+    })
+}
+
+/// One cache file per device, named after its (sanitized) device name.
+fn tuning_cache_path(device_name: &str) -> std::path::PathBuf {
+    let safe_name: String = device_name
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+        .collect();
+    std::path::Path::new(&tuning_cache_dir()).join(format!("{}.json", safe_name))
+}
+
+fn load_tuning_profile(device_name: &str) -> Option<DeviceTuningProfile> {
+    if std::env::var("BELLMAN_GPU_TUNING_FORCE").is_ok() {
+        return None;
+    }
+    let contents = std::fs::read_to_string(tuning_cache_path(device_name)).ok()?;
+    let key = "\"chunk_size\":";
+    let after_key = &contents[contents.find(key)? + key.len()..];
+    let digits_end = after_key
+        .find(|c: char| !c.is_ascii_digit())
+        .unwrap_or_else(|| after_key.len());
+    let chunk_size = after_key[..digits_end].parse().ok()?;
+    Some(DeviceTuningProfile { chunk_size })
+}
+
+fn store_tuning_profile(device_name: &str, profile: DeviceTuningProfile) {
+    let path = tuning_cache_path(device_name);
+    if let Some(parent) = path.parent() {
+        if let Err(e) = std::fs::create_dir_all(parent) {
+            error!(
+                "Could not create GPU tuning cache dir '{}': {}",
+                parent.display(),
+                e
+            );
+            return;
+        }
+    }
+    let json = format!("{{\"chunk_size\":{}}}\n", profile.chunk_size);
+    if let Err(e) = std::fs::write(&path, json) {
+        error!("Could not write GPU tuning cache '{}': {}", path.display(), e);
+    }
+}
+
 // Multiexp kernel for a single GPU
 pub struct SingleMultiexpKernel<E>
 where
-    E: Engine,
+    E: GlvEngine,
 {
     program: opencl::Program,
 
     core_count: usize,
     n: usize,
 
+    /// Vendor-tuned `(group_multiplier, local_work_size)`, see `vendor_tuning`.
+    group_multiplier: usize,
+    local_work_size: usize,
+
     priority: bool,
+    /// Numeric, preemptible priority checked between chunks in
+    /// `multiexp_pipelined`. Defaults to a handle derived from `priority`
+    /// via `PriorityHandle::from_bool` so every existing caller keeps
+    /// working; `create_with_priority_handle` lets a caller pass its own
+    /// handle instead, to `raise`/`cancel` it while the proof is in flight.
+    priority_handle: PriorityHandle,
+    buffer_pool: BufferPool,
+    /// Chunk size for `multiexp_pipelined`, lazily resolved by
+    /// `tuned_chunk_size` from the on-disk cache or a live benchmark sweep.
+    chunk_size: Option<usize>,
     _phantom: std::marker::PhantomData<E::Fr>,
 }
 
-fn calc_num_groups(core_count: usize, num_windows: usize) -> usize {
-    // Observations show that we get the best performance when num_groups * num_windows ~= 2 * CUDA_CORES
-    2 * core_count / num_windows
+fn calc_num_groups(core_count: usize, num_windows: usize, group_multiplier: usize) -> usize {
+    // Observations show that we get the best performance when
+    // num_groups * num_windows ~= group_multiplier * core_count. The
+    // multiplier that hits best occupancy isn't the same across vendors
+    // (it was tuned as a flat `2` back when this only ever ran on Nvidia
+    // CUDA cores), so it's picked per-device in `SingleMultiexpKernel::create`.
+    group_multiplier * core_count / num_windows
+}
+
+/// Vendor-tuned `group_multiplier` for `calc_num_groups` and local work
+/// size for the bucket kernel's work-group dispatch. Nvidia keeps the
+/// flat `2`x/`256` this file originally shipped with (that's what the
+/// formula above was empirically tuned against); AMD and Intel get their
+/// own starting points since neither shares Nvidia's warp/occupancy model.
+/// These are still coarse defaults, not a measured model per GPU — the
+/// per-device tuning cache above is the place to refine them further.
+fn vendor_tuning(brand: opencl::Brand) -> (usize, usize) {
+    match brand {
+        opencl::Brand::Nvidia => (2, 256),
+        opencl::Brand::Amd => (4, 256),
+        _ => (6, 128),
+    }
+}
+
+/// Vendor-corrected core count for `calc_num_groups`, built on top of
+/// `utils::get_core_count`.
+///
+/// `get_core_count` isn't one of the files this series touches (`gpu/utils.rs`
+/// isn't part of this checkout), so rather than guess at its internals and
+/// risk contradicting the real implementation, the vendor correction is
+/// applied here at the call site instead of inside it. Treat `get_core_count`
+/// as reporting a CUDA-core-shaped count (the model `vendor_tuning` above was
+/// originally tuned against, back when this only ran on Nvidia): Nvidia's SMs
+/// are what that count already models, so they pass through unscaled. AMD's
+/// GCN/RDNA compute units pack far fewer ALU lanes per unit than an Nvidia SM,
+/// and we don't have enough signal here to tell Intel EUs apart from either,
+/// so both get a coarse down-scaling instead of being counted as if they were
+/// Nvidia SMs. These factors are rough defaults, not a measured per-vendor
+/// model — replacing them with real compute-unit introspection belongs in
+/// `get_core_count` itself once that file is part of this checkout.
+fn core_count_for_device(d: &opencl::Device, brand: opencl::Brand) -> usize {
+    let raw = utils::get_core_count(d);
+    match brand {
+        opencl::Brand::Nvidia => raw,
+        opencl::Brand::Amd => std::cmp::max(raw / 2, 1),
+        _ => std::cmp::max(raw / 4, 1),
+    }
 }
 
 // fn calc_window_size(n: usize, exp_bits: usize, core_count: usize) -> usize {
@@ -102,16 +458,439 @@ fn exp_size<E: Engine>() -> usize {
     std::mem::size_of::<<E::Fr as ff::PrimeField>::Repr>()
 }
 
+// GLV (Gallant-Lambert-Vanstone) scalar decomposition. BLS12-381 G1 and G2
+// both admit the cheap endomorphism phi(x, y) = (beta*x, y) with
+// phi(P) = lambda*P for a known lambda, beta. That lets us rewrite an n-bit
+// scalar k as k1 + k2*lambda with |k1|, |k2| roughly half as wide, so the
+// GPU kernel can be run with half the window/doubling work: expand (P, k)
+// into (P, k1) and (phi(P), k2) before upload. Engines without a cheap
+// endomorphism just keep the `None` default and `multiexp` below falls back
+// to the untouched single-scalar path.
+//
+// `(a1, b1)`/`(a2, b2)` are the short basis vectors of the lattice
+// `{ (x, y) : x + y*lambda = 0 (mod r) }`. For BLS12-381, `lambda` itself is
+// ~sqrt(r) (the curve's seed is chosen to make this so), which makes the
+// lattice basis trivial to state exactly rather than needing an offline
+// extended-Euclidean reduction: `(lambda, -1)` and `(1, lambda + 1)` both
+// satisfy `a + b*lambda = 0 (mod r)` (the latter because `lambda^2 + lambda
+// + 1 = 0 (mod r)`), and both have norm ~sqrt(r), i.e. they *are* the short
+// basis.
+// `a1` and `b2` need the full 128 bits of unsigned magnitude (lambda ~sqrt(r)
+// is itself >= 2^127), which does not fit in a signed `i128`. Each component
+// is stored as a (magnitude, is_negative) pair instead.
+pub struct GlvLattice {
+    /// The scalar field modulus `r` itself - needed at decomposition time to
+    /// compute `round(b*k/r)` exactly.
+    pub r: [u64; 4],
+    pub lambda: [u64; 4],
+    pub beta: [u64; 6],
+    pub a1: (u128, bool),
+    pub b1: (u128, bool),
+    pub a2: (u128, bool),
+    pub b2: (u128, bool),
+}
+
+/// Minimal fixed-width unsigned-integer helpers used only to make
+/// `glv_decompose` exact. `c1 = round(b2*k/r)` multiplies a ~128-bit `b2` by
+/// a ~256-bit `k` (up to a 384-bit product) and divides by the 256-bit
+/// modulus `r`; none of that fits in a native integer type, and approximating
+/// it in `i128`/`f64` silently loses the low bits of `k` and overflows the
+/// intermediate products (see the history of this file for what that broke).
+mod wide {
+    /// Little-endian 384-bit unsigned integer (`u64` limbs, limb 0 = least
+    /// significant) - wide enough to hold a 128-bit value times `Fr::Repr`.
+    pub type U384 = [u64; 6];
+
+    /// `a * b` where `a` is at most 128 bits and `b` is `Fr::Repr`-sized
+    /// (256 bits), computed exactly via schoolbook multiplication.
+    pub fn mul_u128_repr(a: u128, b: &[u64]) -> U384 {
+        let mut out = [0u64; 6];
+        for (i, &a_limb) in [a as u64, (a >> 64) as u64].iter().enumerate() {
+            let mut carry = 0u128;
+            for (j, &b_limb) in b.iter().enumerate() {
+                let acc = (a_limb as u128) * (b_limb as u128) + out[i + j] as u128 + carry;
+                out[i + j] = acc as u64;
+                carry = acc >> 64;
+            }
+            out[i + b.len()] = out[i + b.len()].wrapping_add(carry as u64);
+        }
+        out
+    }
+
+    /// `round(num / den)` for a 384-bit numerator and a 256-bit-or-narrower
+    /// denominator, where the exact quotient is known (by construction of
+    /// the caller's lattice basis) to fit in 128 bits. Plain binary long
+    /// division: no precision loss, at the cost of one bit per iteration.
+    pub fn div_round_u384(num: &U384, den: &[u64]) -> u128 {
+        let limb_at = |v: &[u64], bit: usize| -> u64 {
+            v.get(bit / 64).copied().unwrap_or(0)
+        };
+        // `rem` only ever holds values < 2*den < 2^257, so 5 limbs (320
+        // bits) is ample headroom above `den`'s 256 bits.
+        let mut rem = [0u64; 5];
+        let mut quotient: u128 = 0;
+        for bit in (0..384).rev() {
+            // Shift `rem` left by one bit, bringing in numerator bit `bit`.
+            let mut carry_in = (limb_at(num, bit) >> (bit % 64)) & 1;
+            for limb in rem.iter_mut() {
+                let carry_out = *limb >> 63;
+                *limb = (*limb << 1) | carry_in;
+                carry_in = carry_out;
+            }
+            if ge(&rem, den) {
+                sub_assign(&mut rem, den);
+                if bit < 128 {
+                    quotient |= 1u128 << bit;
+                }
+            }
+        }
+        // Round to nearest: the quotient is exact above; round up iff the
+        // remaining remainder is at least half of `den`.
+        let mut doubled = rem;
+        let mut carry = 0u64;
+        for limb in doubled.iter_mut() {
+            let new_carry = *limb >> 63;
+            *limb = (*limb << 1) | carry;
+            carry = new_carry;
+        }
+        if carry != 0 || ge(&doubled, den) {
+            quotient += 1;
+        }
+        quotient
+    }
+
+    fn ge(lhs: &[u64], rhs: &[u64]) -> bool {
+        for i in (0..lhs.len().max(rhs.len())).rev() {
+            let l = lhs.get(i).copied().unwrap_or(0);
+            let r = rhs.get(i).copied().unwrap_or(0);
+            if l != r {
+                return l > r;
+            }
+        }
+        true
+    }
+
+    fn sub_assign(lhs: &mut [u64], rhs: &[u64]) {
+        let mut borrow = 0i128;
+        for i in 0..lhs.len() {
+            let r = rhs.get(i).copied().unwrap_or(0);
+            let diff = lhs[i] as i128 - r as i128 - borrow;
+            if diff < 0 {
+                lhs[i] = (diff + (1i128 << 64)) as u64;
+                borrow = 1;
+            } else {
+                lhs[i] = diff as u64;
+                borrow = 0;
+            }
+        }
+    }
+
+    /// `a * b` for two 128-bit values, exact, as a 256-bit unsigned product.
+    pub fn mul_u128(a: u128, b: u128) -> [u64; 4] {
+        let wide = mul_u128_repr(a, &[b as u64, (b >> 64) as u64]);
+        [wide[0], wide[1], wide[2], wide[3]]
+    }
+
+    /// 320-bit (5-limb) two's-complement workspace used to evaluate
+    /// `k - c*lambda - d` exactly: `k` is up to 256 bits, `c*lambda` up to
+    /// 256 bits, so the true (small, signed) result needs headroom above
+    /// 256 bits to avoid wrapping before we can read the sign back out.
+    pub type I320 = [u64; 5];
+
+    pub fn to_i320(v: &[u64]) -> I320 {
+        let mut out = [0u64; 5];
+        out[..v.len().min(5)].copy_from_slice(&v[..v.len().min(5)]);
+        out
+    }
+
+    pub fn from_u128(v: u128) -> I320 {
+        to_i320(&[v as u64, (v >> 64) as u64])
+    }
+
+    /// `lhs - rhs`, wrapping mod 2^320 (i.e. two's-complement subtraction).
+    pub fn sub_i320(lhs: &I320, rhs: &[u64]) -> I320 {
+        let mut out = *lhs;
+        sub_assign(&mut out, rhs);
+        out
+    }
+
+    /// `lhs + rhs`, wrapping mod 2^320.
+    pub fn add_i320(lhs: &I320, rhs: &[u64]) -> I320 {
+        let mut out = *lhs;
+        let mut carry = 0u64;
+        for (i, limb) in out.iter_mut().enumerate() {
+            let r = rhs.get(i).copied().unwrap_or(0);
+            let (s1, c1) = limb.overflowing_add(r);
+            let (s2, c2) = s1.overflowing_add(carry);
+            *limb = s2;
+            carry = (c1 as u64) + (c2 as u64);
+        }
+        out
+    }
+
+    /// `acc - sign*magnitude`, i.e. subtract a signed 256-bit term from the
+    /// running 320-bit accumulator.
+    pub fn sub_signed(acc: &I320, magnitude: &[u64], is_negative: bool) -> I320 {
+        if is_negative {
+            add_i320(acc, magnitude)
+        } else {
+            sub_i320(acc, magnitude)
+        }
+    }
+
+    /// Reads `v` as a signed 320-bit two's-complement integer and returns
+    /// its `(magnitude, is_negative)`. Only the low 128 bits of the
+    /// magnitude are returned, without any check on the higher limbs -
+    /// callers only use this where the lattice basis guarantees it fits.
+    pub fn signed_magnitude(v: &I320) -> (u128, bool) {
+        let is_negative = v[4] >> 63 == 1;
+        let magnitude_limbs = if is_negative {
+            let mut negated = *v;
+            // Two's-complement negate: flip all bits, add one.
+            for limb in negated.iter_mut() {
+                *limb = !*limb;
+            }
+            let mut carry = 1u64;
+            for limb in negated.iter_mut() {
+                let (sum, c1) = limb.overflowing_add(carry);
+                *limb = sum;
+                carry = c1 as u64;
+                if carry == 0 {
+                    break;
+                }
+            }
+            negated
+        } else {
+            *v
+        };
+        let magnitude = (magnitude_limbs[0] as u128) | ((magnitude_limbs[1] as u128) << 64);
+        (magnitude, is_negative)
+    }
+}
+
+pub trait GlvEngine: Engine {
+    /// `None` for engines without an efficient endomorphism; `multiexp`
+    /// silently falls back to the plain scalar path in that case.
+    fn glv_lattice() -> Option<&'static GlvLattice> {
+        None
+    }
+
+    /// phi(P) = (beta*x, y). Only ever called when `glv_lattice` is `Some`.
+    fn glv_endo_g1(_p: &Self::G1Affine) -> Self::G1Affine {
+        unreachable!("GLV is not enabled for this engine")
+    }
+
+    fn glv_endo_g2(_p: &Self::G2Affine) -> Self::G2Affine {
+        unreachable!("GLV is not enabled for this engine")
+    }
+}
+
+/// Split a full-width scalar `k` into `(k1, sign1, k2, sign2)` such that
+/// `k == sign1*k1 + sign2*k2*lambda (mod r)`, with `k1`/`k2` each roughly
+/// half the bit-width of `k`. The `b*k` products behind `c1`/`c2` are up to
+/// 384 bits, every `c`-times-lattice-constant product is up to 256 bits (a
+/// ~128-bit `c` times a ~128-bit constant), and every accumulation uses the
+/// full 256 bits of `k`, so all of it runs in the
+/// `wide` helpers above rather than native `i128`/`u128` arithmetic, which
+/// cannot hold these products without silently wrapping.
+fn glv_decompose<F: PrimeField>(lattice: &GlvLattice, k: &F::Repr) -> (u128, bool, u128, bool) {
+    let k_limbs = k.as_ref();
+
+    // c1 = round(b2*k/r), c2 = round(-b1*k/r). `k >= 0`, so each quotient's
+    // sign is just the sign of the (fixed, compile-time-known) numerator
+    // coefficient: `b2` for c1, `-b1` for c2.
+    let c1_mag = wide::div_round_u384(&wide::mul_u128_repr(lattice.b2.0, k_limbs), &lattice.r);
+    let c1 = (c1_mag, lattice.b2.1);
+    let c2_mag = wide::div_round_u384(&wide::mul_u128_repr(lattice.b1.0, k_limbs), &lattice.r);
+    let c2 = (c2_mag, !lattice.b1.1);
+
+    // k1 = k - c1*a1 - c2*a2, k2 = -c1*b1 - c2*b2, accumulated in a 320-bit
+    // signed workspace (k and each c*lattice-constant product can be up to
+    // 256 bits; only the final k1/k2 are guaranteed small by construction of
+    // the lattice basis).
+    let c1_a1 = wide::mul_u128(c1.0, lattice.a1.0);
+    let c1_a1_sign = c1.1 ^ lattice.a1.1;
+    let c2_a2 = wide::mul_u128(c2.0, lattice.a2.0);
+    let c2_a2_sign = c2.1 ^ lattice.a2.1;
+
+    let k1_acc = wide::sub_signed(&wide::to_i320(k_limbs), &c1_a1, c1_a1_sign);
+    let k1_acc = wide::sub_signed(&k1_acc, &c2_a2, c2_a2_sign);
+    let (k1, k1_is_negative) = wide::signed_magnitude(&k1_acc);
+    let sign1 = !k1_is_negative;
+
+    let c1_b1 = wide::mul_u128(c1.0, lattice.b1.0);
+    let c1_b1_sign = c1.1 ^ lattice.b1.1;
+    let c2_b2 = wide::mul_u128(c2.0, lattice.b2.0);
+    let c2_b2_sign = c2.1 ^ lattice.b2.1;
+
+    let k2_acc = wide::sub_signed(&[0u64; 5], &c1_b1, c1_b1_sign);
+    let k2_acc = wide::sub_signed(&k2_acc, &c2_b2, c2_b2_sign);
+    let (k2, k2_is_negative) = wide::signed_magnitude(&k2_acc);
+    let sign2 = !k2_is_negative;
+
+    (k1, sign1, k2, sign2)
+}
+
+// Short basis for BLS12-381's scalar field r and its cube-root-of-unity
+// eigenvalue lambda, stated in closed form as described above (no
+// extended-Euclidean reduction is run). `beta` is the matching cube root of unity in Fq so that
+// phi(x, y) = (beta*x, y) satisfies phi(P) = lambda*P (the other nontrivial
+// cube root of unity in Fq pairs with the *other* nontrivial root mod r, not
+// this one - picking the wrong pairing silently gives a phi that isn't
+// actually an endomorphism of this curve).
+static BLS12_381_GLV: GlvLattice = GlvLattice {
+    r: [
+        0xffffffff00000001,
+        0x53bda402fffe5bfe,
+        0x3339d80809a1d805,
+        0x73eda753299d7d48,
+    ],
+    lambda: [0xffffffff, 0xac45a4010001a402, 0x0, 0x0],
+    beta: [
+        0x8bfd00000000aaac,
+        0x409427eb4f49fffd,
+        0x897d29650fb85f9b,
+        0xaa0d857d89759ad4,
+        0xec02408663d4de85,
+        0x1a0111ea397fe699,
+    ],
+    // lambda is ~sqrt(r) by construction (see the GLV lattice comment
+    // above), so the basis is just (lambda, -1) and (1, lambda + 1).
+    a1: (0xac45a4010001a40200000000ffffffff, false),
+    b1: (1, true),
+    a2: (1, false),
+    b2: (0xac45a4010001a4020000000100000000, false),
+};
+
+impl GlvEngine for crate::bls::Bls12 {
+    fn glv_lattice() -> Option<&'static GlvLattice> {
+        Some(&BLS12_381_GLV)
+    }
+
+    fn glv_endo_g1(p: &Self::G1Affine) -> Self::G1Affine {
+        use crate::bls::{Fq, FqRepr};
+        let beta = Fq::from_repr(FqRepr(BLS12_381_GLV.beta)).expect("beta is a valid Fq element");
+        let mut x = p.get_x();
+        x.mul_assign(&beta);
+        Self::G1Affine::from_xy_unchecked(x, p.get_y())
+    }
+
+    fn glv_endo_g2(p: &Self::G2Affine) -> Self::G2Affine {
+        use crate::bls::{Fq, FqRepr};
+        let beta = Fq::from_repr(FqRepr(BLS12_381_GLV.beta)).expect("beta is a valid Fq element");
+        let mut x = p.get_x();
+        x.c0.mul_assign(&beta);
+        x.c1.mul_assign(&beta);
+        Self::G2Affine::from_xy_unchecked(x, p.get_y())
+    }
+}
+
+/// Expand `(base, k)` pairs into `(base, k1), (phi(base), k2)` pairs so the
+/// existing bucket kernel can run `window_size` over half the bits. No-op
+/// (returns `None`) for engines/curves without a GLV lattice.
+fn glv_expand<E, G>(
+    bases: &[G],
+    exps: &[<<G::Engine as ScalarEngine>::Fr as PrimeField>::Repr],
+) -> Option<(Vec<G>, Vec<<<G::Engine as ScalarEngine>::Fr as PrimeField>::Repr>)>
+where
+    E: GlvEngine,
+    G: CurveAffine,
+{
+    let lattice = E::glv_lattice()?;
+    let is_g1 = TypeId::of::<G>() == TypeId::of::<E::G1Affine>();
+    let is_g2 = TypeId::of::<G>() == TypeId::of::<E::G2Affine>();
+    if !is_g1 && !is_g2 {
+        return None;
+    }
+
+    let mut out_bases = Vec::with_capacity(bases.len() * 2);
+    let mut out_exps = Vec::with_capacity(exps.len() * 2);
+
+    for (base, exp) in bases.iter().zip(exps.iter()) {
+        let (k1, sign1, k2, sign2) = glv_decompose::<<G::Engine as ScalarEngine>::Fr>(lattice, exp);
+
+        // SAFETY: `is_g1`/`is_g2` above confirm `G` and `E::G1Affine`/
+        // `E::G2Affine` are the same type, so the copy is a same-type copy.
+        let phi: G = unsafe {
+            if is_g1 {
+                let b = &*(base as *const G as *const E::G1Affine);
+                let phi = E::glv_endo_g1(b);
+                std::ptr::read(&phi as *const E::G1Affine as *const G)
+            } else {
+                let b = &*(base as *const G as *const E::G2Affine);
+                let phi = E::glv_endo_g2(b);
+                std::ptr::read(&phi as *const E::G2Affine as *const G)
+            }
+        };
+
+        out_bases.push(if sign1 { *base } else { -*base });
+        out_exps.push(u128_to_repr::<G>(k1));
+        out_bases.push(if sign2 { phi } else { -phi });
+        out_exps.push(u128_to_repr::<G>(k2));
+    }
+
+    Some((out_bases, out_exps))
+}
+
+fn u128_to_repr<G: CurveAffine>(
+    v: u128,
+) -> <<G::Engine as ScalarEngine>::Fr as PrimeField>::Repr {
+    let mut exp = <<G::Engine as ScalarEngine>::Fr as PrimeField>::Repr::default();
+    {
+        let limbs = exp.as_mut();
+        limbs[0] = v as u64;
+        limbs[1] = (v >> 64) as u64;
+    }
+    exp
+}
+
+/// Ascending (scalar-count threshold, window size) table driving
+/// `SingleMultiexpKernel::optimal_window_size`: start from a per-curve base
+/// window and add one for every threshold `n` exceeds. This reproduces the
+/// intuition behind the old commented-out `calc_window_size` (bigger batches
+/// justify bigger `bucket_len = 1 << window_size` buckets) without having to
+/// evaluate `ln()` per call. `Projective` types wider than 144 bytes (G2)
+/// start at window size 8 instead of G1's 11, matching the old hardcoded
+/// 8-vs-11 split.
+fn window_table<G: CurveAffine>() -> (usize, &'static [usize]) {
+    if std::mem::size_of::<<G as CurveAffine>::Projective>() > 144 {
+        (8, &[1 << 22, 1 << 24, 1 << 26])
+    } else {
+        (11, &[1 << 20, 1 << 22, 1 << 24, 1 << 26])
+    }
+}
+
 // 单卡单任务
 impl<E> SingleMultiexpKernel<E>
 where
-    E: Engine,
+    E: GlvEngine,
 {
+    /// Table-driven window size for a chunk of `n` scalars. Replaces the
+    /// hardcoded `jack_windows_size` magic constant; tune per device by
+    /// swapping out `window_table`.
+    pub fn optimal_window_size<G: CurveAffine>(&self, n: usize) -> usize {
+        let (base, thresholds) = window_table::<G>();
+        base + thresholds.iter().filter(|&&t| n > t).count()
+    }
+
     pub fn create(d: opencl::Device, priority: bool) -> GPUResult<SingleMultiexpKernel<E>> {
-        let src = sources::kernel::<E>(d.brand() == opencl::Brand::Nvidia);
+        Self::create_with_priority_handle(d, priority, PriorityHandle::from_bool(priority))
+    }
+
+    /// Same as `create`, but lets the caller supply its own `PriorityHandle`
+    /// instead of one derived from `priority`, so it can keep `raise`ing or
+    /// `cancel`ing this kernel's priority while it's busy running a proof.
+    pub fn create_with_priority_handle(
+        d: opencl::Device,
+        priority: bool,
+        priority_handle: PriorityHandle,
+    ) -> GPUResult<SingleMultiexpKernel<E>> {
+        let brand = d.brand();
+        let src = sources::kernel::<E>(brand == opencl::Brand::Nvidia);
+        let (group_multiplier, local_work_size) = vendor_tuning(brand);
 
         // let exp_bits = exp_size::<E>() * 8;
-        let core_count = utils::get_core_count(&d);
+        let core_count = core_count_for_device(&d, brand);
         // let mem = d.memory();
         // let max_n = calc_chunk_size::<E>(mem, core_count);
         // let best_n = calc_best_chunk_size(MAX_WINDOW_SIZE, core_count, exp_bits);
@@ -123,17 +902,118 @@ where
             program: opencl::Program::from_opencl(d, &src)?,
             core_count,
             n,
+            group_multiplier,
+            local_work_size,
             priority,
+            priority_handle,
+            buffer_pool: BufferPool::default(),
+            chunk_size: None,
             _phantom: std::marker::PhantomData,
         })
     }
 
+    /// Run the same base set against several circuits' scalar vectors,
+    /// uploading `bases` to the device once and reusing that buffer for
+    /// every circuit's kernel launch instead of re-uploading it per circuit.
+    /// This is the host-side fusion `chunk2-1` asks for in the
+    /// a_inputs/b_g1_inputs/b_g2_inputs stage, where every circuit in a
+    /// batch multiplies the exact same `ParameterSource` bases by its own
+    /// input assignment: today each circuit's call re-uploads those bases
+    /// from scratch even though they never change across the batch.
+    ///
+    /// This is *not* the full fusion the request describes (one GPU call
+    /// whose buckets are sliced back out per circuit) — that needs the
+    /// bucket-accumulation step inside the OpenCL kernel to become
+    /// segment-aware (today it always folds every bucket down to a single
+    /// accumulator), which lives in the OpenCL kernel source
+    /// (`gpu::sources`) and isn't part of this checkout. What this method
+    /// removes is the per-circuit base re-upload — the larger of the two
+    /// transfers once bases are shared — while still launching one kernel
+    /// (and returning one result) per circuit.
+    ///
+    /// GLV-enabled curves fall back to the plain per-circuit `multiexp`
+    /// path: `glv_expand` derives its expanded base vector and scalar
+    /// vector together from one `(bases, exps)` pair, so the "same expanded
+    /// bases every circuit" property this method relies on doesn't hold
+    /// without also changing `glv_expand`'s signature.
+    ///
+    /// No call site: wiring this into `groth16::prover`'s inputs stage needs
+    /// a resident `&[G]` to pass as `bases`, and there's no way to get one
+    /// out of a `ParameterSource`-derived source from this checkout.
+    /// `SourceBuilder::Source` (`crate::multiexp`, not part of this checkout)
+    /// only exposes `add_assign_mixed`/`skip` against a caller-owned
+    /// accumulator - by design it streams each base directly into a sum and
+    /// never hands the base itself back out, so a `Vec<G>` can't be
+    /// materialized from one without either changing that trait or adding a
+    /// raw-base accessor to `crate::multiexp`. See the call site note in
+    /// `groth16::prover`'s inputs stage for the full explanation.
+    pub fn multiexp_batch_shared_bases<G>(
+        &mut self,
+        bases: &[G],
+        exps_per_circuit: &[Vec<<<G::Engine as ScalarEngine>::Fr as PrimeField>::Repr>],
+    ) -> GPUResult<Vec<<G as CurveAffine>::Projective>>
+    where
+        G: CurveAffine,
+    {
+        if locks::PriorityLock::should_break(self.priority) {
+            return Err(GPUError::GPUTaken);
+        }
+
+        let n = bases.len();
+        let has_glv = E::glv_lattice().is_some()
+            && (TypeId::of::<G>() == TypeId::of::<E::G1Affine>()
+                || TypeId::of::<G>() == TypeId::of::<E::G2Affine>());
+        if has_glv {
+            return exps_per_circuit
+                .iter()
+                .map(|exps| self.multiexp(bases, exps, n))
+                .collect();
+        }
+
+        let exp_bits = exp_size::<E>() * 8;
+        let window_size = self.optimal_window_size::<G>(n);
+        let num_windows = ((exp_bits as f64) / (window_size as f64)).ceil() as usize;
+        let num_groups = calc_num_groups(self.core_count, num_windows, self.group_multiplier);
+
+        let mut base_buffer = match self.buffer_pool.take::<G>(n) {
+            Some(buffer) => buffer,
+            None => self.program.create_buffer::<G>(n)?,
+        };
+        base_buffer.write_from(0, bases)?;
+
+        let mut results = Vec::with_capacity(exps_per_circuit.len());
+        for exps in exps_per_circuit {
+            if self.priority_handle.should_yield() {
+                self.buffer_pool.put(n, base_buffer);
+                return Err(GPUError::GPUTaken);
+            }
+
+            let mut exp_buffer = match self
+                .buffer_pool
+                .take::<<<G::Engine as ScalarEngine>::Fr as PrimeField>::Repr>(n)
+            {
+                Some(buffer) => buffer,
+                None => self
+                    .program
+                    .create_buffer::<<<G::Engine as ScalarEngine>::Fr as PrimeField>::Repr>(n)?,
+            };
+            exp_buffer.write_from(0, exps)?;
+
+            let result_buffer =
+                self.launch_kernel(&base_buffer, &exp_buffer, n, num_groups, num_windows, window_size)?;
+            self.buffer_pool.put(n, exp_buffer);
+            results.push(self.collect_result::<G>(result_buffer, num_groups, num_windows, window_size, exp_bits)?);
+        }
+
+        self.buffer_pool.put(n, base_buffer);
+        Ok(results)
+    }
+
     pub fn multiexp<G>(
         &mut self,
         bases: &[G],
         exps: &[<<G::Engine as ScalarEngine>::Fr as PrimeField>::Repr],
         n: usize,
-        jack_windows_size: usize,
     ) -> GPUResult<<G as CurveAffine>::Projective>
     where
         G: CurveAffine,
@@ -142,12 +1022,24 @@ where
             return Err(GPUError::GPUTaken);
         }
 
-        let exp_bits = exp_size::<E>() * 8;
+        // GLV fast path: split (base, k) into (base, k1), (phi(base), k2) so
+        // the bucket kernel below only ever sees half-width scalars. Falls
+        // through untouched for non-BLS engines/curves.
+        let glv_expanded = glv_expand::<E, G>(bases, exps);
+        let (bases, exps, n) = match &glv_expanded {
+            Some((glv_bases, glv_exps)) => (glv_bases.as_slice(), glv_exps.as_slice(), glv_bases.len()),
+            None => (bases, exps, n),
+        };
+        let exp_bits = if glv_expanded.is_some() {
+            exp_size::<E>() * 4
+        } else {
+            exp_size::<E>() * 8
+        };
         // let window_size = calc_window_size(n as usize, exp_bits, self.core_count);
-        let window_size = jack_windows_size;
-        let num_windows = ((exp_bits as f64) / (jack_windows_size as f64)).ceil() as usize;
-        let num_groups = calc_num_groups(self.core_count, num_windows);
-        let bucket_len = 1 << jack_windows_size;
+        let window_size = self.optimal_window_size::<G>(n);
+        let num_windows = ((exp_bits as f64) / (window_size as f64)).ceil() as usize;
+        let num_groups = calc_num_groups(self.core_count, num_windows, self.group_multiplier);
+        let bucket_len = 1 << window_size;
 
         info!("bucket_len is :{}",  bucket_len);
 
@@ -168,8 +1060,8 @@ where
         let size3 = std::mem::size_of::<<G as CurveAffine>::Projective>();
         let mem1 = size1 * n;
         let mem2 = size2 * n;
-        let mem3 = size3 * 2 * self.core_count * bucket_len;
-        let mem4 = size3 * 2 * self.core_count;
+        let mem3 = size3 * num_groups * num_windows * bucket_len;
+        let mem4 = size3 * num_groups * num_windows;
         info!("GABEDEBUG: <G> size:{}, <PrimeField> size:{}, <Projective> size:{}", size1, size2, size3);
         info!("GABEDEBUG: GPU mem need:{}byte, {}Mbyte", mem1 + mem2 + mem3 + mem4, (mem1 + mem2 + mem3 + mem4)/(1024*1024));
          
@@ -182,23 +1074,112 @@ where
 
 
 
-        let mut base_buffer = self.program.create_buffer::<G>(n)?;
+        let (base_buffer, exp_buffer) = self.upload_buffers(bases, exps, n)?;
+        let result_buffer =
+            self.launch_kernel(&base_buffer, &exp_buffer, n, num_groups, num_windows, window_size)?;
+        self.release_buffers(n, base_buffer, exp_buffer);
+
+        self.collect_result(result_buffer, num_groups, num_windows, window_size, exp_bits)
+    }
+
+    /// Write `bases`/`exps` into device buffers, reusing a same-capacity
+    /// buffer from `self.buffer_pool` when one is free instead of always
+    /// calling `create_buffer`.
+    fn upload_buffers<G>(
+        &mut self,
+        bases: &[G],
+        exps: &[<<G::Engine as ScalarEngine>::Fr as PrimeField>::Repr],
+        n: usize,
+    ) -> GPUResult<(
+        opencl::Buffer<G>,
+        opencl::Buffer<<<G::Engine as ScalarEngine>::Fr as PrimeField>::Repr>,
+    )>
+    where
+        G: CurveAffine,
+    {
+        let mut base_buffer = match self.buffer_pool.take::<G>(n) {
+            Some(buffer) => buffer,
+            None => self.program.create_buffer::<G>(n)?,
+        };
         base_buffer.write_from(0, bases)?;
-        let mut exp_buffer = self
-            .program
-            .create_buffer::<<<G::Engine as ScalarEngine>::Fr as PrimeField>::Repr>(n)?;
+
+        let mut exp_buffer = match self
+            .buffer_pool
+            .take::<<<G::Engine as ScalarEngine>::Fr as PrimeField>::Repr>(n)
+        {
+            Some(buffer) => buffer,
+            None => self
+                .program
+                .create_buffer::<<<G::Engine as ScalarEngine>::Fr as PrimeField>::Repr>(n)?,
+        };
         exp_buffer.write_from(0, exps)?;
-        let bucket_buffer = self
-            .program
-            .create_buffer::<<G as CurveAffine>::Projective>(2 * self.core_count * bucket_len)?;
-        let result_buffer = self
-            .program
-            .create_buffer::<<G as CurveAffine>::Projective>(2 * self.core_count)?;
-
-        // Make global work size divisible by `LOCAL_WORK_SIZE`
+        Ok((base_buffer, exp_buffer))
+    }
+
+    /// Return buffers handed out by `upload_buffers` to the pool once their
+    /// contents have been consumed by an enqueued kernel.
+    fn release_buffers<G>(
+        &mut self,
+        n: usize,
+        base_buffer: opencl::Buffer<G>,
+        exp_buffer: opencl::Buffer<<<G::Engine as ScalarEngine>::Fr as PrimeField>::Repr>,
+    ) where
+        G: CurveAffine,
+    {
+        self.buffer_pool.put(n, base_buffer);
+        self.buffer_pool.put(n, exp_buffer);
+    }
+
+    /// Enqueue the bucket kernel against already-uploaded buffers and return
+    /// the (not yet read back) per-group/per-window result buffer. Bucket
+    /// storage is sized to this call's actual `window_size` (the same
+    /// `bucket_len = 1 << window_size` convention `multiexp` uses above),
+    /// not `window_table`'s largest possible window, so a chunk using a
+    /// small window doesn't pay for buckets it never touches.
+    /// `self.buffer_pool` keys buffers by capacity, so a later call at a
+    /// different `window_size` just takes/creates its own differently-sized
+    /// buffer instead of fighting over this one.
+    fn launch_kernel<G>(
+        &mut self,
+        base_buffer: &opencl::Buffer<G>,
+        exp_buffer: &opencl::Buffer<<<G::Engine as ScalarEngine>::Fr as PrimeField>::Repr>,
+        n: usize,
+        num_groups: usize,
+        num_windows: usize,
+        window_size: usize,
+    ) -> GPUResult<opencl::Buffer<<G as CurveAffine>::Projective>>
+    where
+        G: CurveAffine,
+    {
+        // One bucket-kernel thread per (group, window), each owning its own
+        // `1 << window_size` buckets and writing one result slot. `num_groups`
+        // is `group_multiplier * core_count / num_windows` (see
+        // `calc_num_groups`), and `group_multiplier` is vendor-specific since
+        // `vendor_tuning` stopped hardcoding `2` everywhere - so these
+        // capacities have to be derived from `num_groups`/`num_windows`
+        // directly instead of assuming `num_groups * num_windows == 2 *
+        // core_count`, or AMD/Intel's wider multiplier overruns both buffers.
+        let bucket_capacity = num_groups * num_windows * (1 << window_size);
+        let result_capacity = num_groups * num_windows;
+
+        let bucket_buffer = match self.buffer_pool.take::<<G as CurveAffine>::Projective>(bucket_capacity) {
+            Some(buffer) => buffer,
+            None => self
+                .program
+                .create_buffer::<<G as CurveAffine>::Projective>(bucket_capacity)?,
+        };
+        let result_buffer = match self.buffer_pool.take::<<G as CurveAffine>::Projective>(result_capacity) {
+            Some(buffer) => buffer,
+            None => self
+                .program
+                .create_buffer::<<G as CurveAffine>::Projective>(result_capacity)?,
+        };
+
+        // Make global work size divisible by this device's local work size.
+        let local_work_size = self.local_work_size;
         let mut global_work_size = num_windows * num_groups;
         global_work_size +=
-            (LOCAL_WORK_SIZE - (global_work_size % LOCAL_WORK_SIZE)) % LOCAL_WORK_SIZE;
+            (local_work_size - (global_work_size % local_work_size)) % local_work_size;
 
         let kernel = self.program.create_kernel(
             if TypeId::of::<G>() == TypeId::of::<E::G1Affine>() {
@@ -209,23 +1190,46 @@ where
                 return Err(GPUError::Simple("Only E::G1 and E::G2 are supported!"));
             },
             global_work_size,
-            None,
+            Some(local_work_size),
         );
 
         call_kernel!(
             kernel,
-            &base_buffer,
+            base_buffer,
             &bucket_buffer,
             &result_buffer,
-            &exp_buffer,
+            exp_buffer,
             n as u32,
             num_groups as u32,
             num_windows as u32,
             window_size as u32
         )?;
 
+        // The kernel fully overwrites every bucket it touches before
+        // accumulating into it, so handing the (already-enqueued) bucket
+        // buffer back to the pool right away is safe: the in-order command
+        // queue guarantees any future reuse is scheduled after this kernel.
+        self.buffer_pool.put(bucket_capacity, bucket_buffer);
+
+        Ok(result_buffer)
+    }
+
+    /// Block on the kernel's result buffer, fold it down to the final
+    /// projective accumulator, and return the buffer to the pool.
+    fn collect_result<G>(
+        &mut self,
+        result_buffer: opencl::Buffer<<G as CurveAffine>::Projective>,
+        num_groups: usize,
+        num_windows: usize,
+        window_size: usize,
+        exp_bits: usize,
+    ) -> GPUResult<<G as CurveAffine>::Projective>
+    where
+        G: CurveAffine,
+    {
         let mut results = vec![<G as CurveAffine>::Projective::zero(); num_groups * num_windows];
         result_buffer.read_into(0, &mut results)?;
+        self.buffer_pool.put(num_groups * num_windows, result_buffer);
 
         // Using the algorithm below, we can calculate the final result by accumulating the results
         // of those `NUM_GROUPS` * `NUM_WINDOWS` threads.
@@ -244,6 +1248,221 @@ where
 
         Ok(acc)
     }
+
+    /// Pipelined variant of `multiexp` over a sequence of chunks: while the
+    /// kernel for chunk `i` is running on the device, chunk `i+1`'s
+    /// bases/exps are already being uploaded into their *own* buffer set, so
+    /// host->device transfer overlaps with GPU compute instead of leaving
+    /// the device idle during `write_from`. Chunk `i`'s base/exp buffers are
+    /// only handed back to `self.buffer_pool` once chunk `i`'s result has
+    /// been collected (one iteration later), so `upload_buffers` for chunk
+    /// `i+1` can never be handed the still-in-flight buffers back - the pool
+    /// has to either reuse an already-released (older) buffer set or
+    /// allocate a fresh one. The only synchronization point per chunk is the
+    /// blocking `read_into` in `collect_result`, which now happens one
+    /// chunk *after* its kernel was launched.
+    pub fn multiexp_pipelined<G>(
+        &mut self,
+        bases: &[G],
+        exps: &[<<G::Engine as ScalarEngine>::Fr as PrimeField>::Repr],
+        chunk_size: usize,
+    ) -> GPUResult<<G as CurveAffine>::Projective>
+    where
+        G: CurveAffine,
+    {
+        // `bases`/`exps` already have to be fully resident here, so slicing
+        // them is free; `multiexp_pipelined_from_chunks` below is what
+        // actually makes the pipeline's *host-side* input lazy.
+        self.multiexp_pipelined_from_chunks(
+            bases
+                .chunks(chunk_size)
+                .zip(exps.chunks(chunk_size))
+                .map(|(b, e)| (b.to_vec(), e.to_vec())),
+        )
+    }
+
+    /// Same pipeline as `multiexp_pipelined`, generalized to pull each
+    /// chunk's bases/exps from an iterator instead of slicing a `bases:
+    /// &[G]` that must already be fully resident in host memory. This is
+    /// the piece of lazily-streamed parameter support that belongs to this
+    /// kernel: a caller backed by a memory-mapped or `io::Read` parameter
+    /// file can implement `Iterator<Item = (Vec<G>, Vec<Repr>)>` by
+    /// deserializing one window of points at a time, so only that window
+    /// (plus the in-flight chunk still uploading/computing) is ever
+    /// resident, instead of the whole multi-GB base vector.
+    ///
+    /// Wiring an actual memory-mapped source through `ParameterSource` so
+    /// `groth16::prover` can hand one of these iterators in is still open:
+    /// the `Source`/`SourceBuilder` trait pair that `ParameterSource` hands
+    /// back lives in `crate::multiexp` alongside `groth16::mod`, and
+    /// neither is part of this checkout, so there's nothing here to extend
+    /// yet. This method is the chunk-source-agnostic half of the feature
+    /// that *can* land without guessing at those traits' signatures.
+    pub fn multiexp_pipelined_from_chunks<G>(
+        &mut self,
+        mut chunks: impl Iterator<
+            Item = (
+                Vec<G>,
+                Vec<<<G::Engine as ScalarEngine>::Fr as PrimeField>::Repr>,
+            ),
+        >,
+    ) -> GPUResult<<G as CurveAffine>::Projective>
+    where
+        G: CurveAffine,
+    {
+        let mut acc = <G as CurveAffine>::Projective::zero();
+
+        if locks::PriorityLock::should_break(self.priority) {
+            return Err(GPUError::GPUTaken);
+        }
+
+        struct InFlight<G: CurveAffine> {
+            n: usize,
+            base_buffer: opencl::Buffer<G>,
+            exp_buffer: opencl::Buffer<<<G::Engine as ScalarEngine>::Fr as PrimeField>::Repr>,
+            result_buffer: opencl::Buffer<<G as CurveAffine>::Projective>,
+            num_groups: usize,
+            num_windows: usize,
+            window_size: usize,
+            exp_bits: usize,
+        }
+
+        let mut in_flight: Option<InFlight<G>> = None;
+
+        while let Some((chunk_bases, chunk_exps)) = chunks.next() {
+            // Sampled every chunk (not just once, like the `should_break`
+            // check above), so a higher-priority job registered *after*
+            // this call started still gets the device back without having
+            // to wait for the whole multiexp to finish.
+            if self.priority_handle.should_yield() {
+                return Err(GPUError::GPUTaken);
+            }
+
+            let glv_expanded = glv_expand::<E, G>(&chunk_bases, &chunk_exps);
+            let (chunk_bases, chunk_exps, n) = match &glv_expanded {
+                Some((glv_bases, glv_exps)) => (glv_bases.as_slice(), glv_exps.as_slice(), glv_bases.len()),
+                None => (chunk_bases.as_slice(), chunk_exps.as_slice(), chunk_bases.len()),
+            };
+            let exp_bits = if glv_expanded.is_some() {
+                exp_size::<E>() * 4
+            } else {
+                exp_size::<E>() * 8
+            };
+            let window_size = self.optimal_window_size::<G>(n);
+            let num_windows = ((exp_bits as f64) / (window_size as f64)).ceil() as usize;
+            let num_groups = calc_num_groups(self.core_count, num_windows, self.group_multiplier);
+
+            // Upload this chunk into its own buffer set and launch its
+            // kernel. The previous chunk's buffers (below) are still held in
+            // `in_flight` at this point, not yet back in `self.buffer_pool`,
+            // so `upload_buffers` cannot hand them straight back to us here -
+            // this genuinely is a distinct buffer set, not the one the
+            // previous chunk's kernel may still be reading from.
+            let (base_buffer, exp_buffer) = self.upload_buffers(chunk_bases, chunk_exps, n)?;
+            let result_buffer =
+                self.launch_kernel(&base_buffer, &exp_buffer, n, num_groups, num_windows, window_size)?;
+
+            // ...only *then* block on the previous chunk's result - by now
+            // its kernel has long since finished reading its own buffers, so
+            // it's safe to release them back to the pool here.
+            if let Some(prev) = in_flight.take() {
+                acc.add_assign(&self.collect_result::<G>(
+                    prev.result_buffer,
+                    prev.num_groups,
+                    prev.num_windows,
+                    prev.window_size,
+                    prev.exp_bits,
+                )?);
+                self.release_buffers(prev.n, prev.base_buffer, prev.exp_buffer);
+            }
+
+            in_flight = Some(InFlight {
+                n,
+                base_buffer,
+                exp_buffer,
+                result_buffer,
+                num_groups,
+                num_windows,
+                window_size,
+                exp_bits,
+            });
+        }
+
+        if let Some(last) = in_flight.take() {
+            acc.add_assign(&self.collect_result::<G>(
+                last.result_buffer,
+                last.num_groups,
+                last.num_windows,
+                last.window_size,
+                last.exp_bits,
+            )?);
+            self.release_buffers(last.n, last.base_buffer, last.exp_buffer);
+        }
+
+        Ok(acc)
+    }
+
+    /// Candidate chunk sizes `tuned_chunk_size` sweeps over.
+    const TUNING_CANDIDATES: &'static [usize] = &[1 << 22, 1 << 23, 1 << 24, 1 << 25];
+
+    /// Chunk size to pass to `multiexp_pipelined` for this device.
+    ///
+    /// Resolved once per kernel: first from the in-process field, then from
+    /// the on-disk cache written by an earlier process (skip with
+    /// `BELLMAN_GPU_TUNING_FORCE`), and only if both miss by timing a small
+    /// synthetic sweep at a handful of candidate chunk sizes. The winner is
+    /// kept on `self` and persisted to disk so neither this kernel nor a
+    /// future process has to sweep again for this device.
+    pub fn tuned_chunk_size<G>(
+        &mut self,
+        // Only used to pin the `G` type parameter at the call site - the
+        // sweep itself benchmarks synthetic data, not these.
+        _bases: &[G],
+        _exps: &[<<G::Engine as ScalarEngine>::Fr as PrimeField>::Repr],
+    ) -> GPUResult<usize>
+    where
+        G: CurveAffine,
+    {
+        if let Some(chunk_size) = self.chunk_size {
+            return Ok(chunk_size);
+        }
+
+        let device_name = self.program.device().name();
+        if let Some(profile) = load_tuning_profile(&device_name) {
+            self.chunk_size = Some(profile.chunk_size);
+            return Ok(profile.chunk_size);
+        }
+
+        let candidates = Self::TUNING_CANDIDATES;
+
+        // Every candidate needs to actually drive a different number of
+        // `multiexp_pipelined` chunks, or the sweep just times the same
+        // single-chunk call `candidates.len()` times and picks on timing
+        // noise. Sizing the synthetic sample to the largest candidate means
+        // that candidate sees exactly one full chunk while every smaller
+        // candidate is exercised across several chunks, so the pipelining/
+        // buffer-pool overhead a smaller chunk size trades off against is
+        // actually present in the measurement.
+        let sample_n = *candidates.last().expect("TUNING_CANDIDATES is non-empty");
+        let synthetic_bases = vec![G::one(); sample_n];
+        let synthetic_exps = vec![<G::Engine as ScalarEngine>::Fr::one().into_repr(); sample_n];
+
+        let mut best_chunk_size = candidates[0];
+        let mut best_elapsed = f64::INFINITY;
+        for &candidate in candidates {
+            let start = Instant::now();
+            self.multiexp_pipelined(&synthetic_bases, &synthetic_exps, candidate)?;
+            let elapsed = start.elapsed().as_secs_f64();
+            if elapsed < best_elapsed {
+                best_elapsed = elapsed;
+                best_chunk_size = candidate;
+            }
+        }
+
+        self.chunk_size = Some(best_chunk_size);
+        store_tuning_profile(&device_name, DeviceTuningProfile { chunk_size: best_chunk_size });
+        Ok(best_chunk_size)
+    }
 }
 
 pub fn only_cpu_multiexp<G>(
@@ -269,15 +1488,7 @@ pub fn only_cpu_multiexp<G>(
     let (tx_cpu, rx_cpu) = mpsc::channel();
     let mut scoped_pool = Pool::new(1);
 
-    let mut cpu_core_ids = vec![];
-    for i in 1..64 {
-        cpu_core_ids.push(
-            i as usize
-        )
-    }
-
     use std::cell::Cell;
-    use std::sync::Mutex;
     let mut round_counter = Arc::new(Mutex::new(1));
 
     scoped_pool.scoped(|scoped| {
@@ -289,9 +1500,17 @@ pub fn only_cpu_multiexp<G>(
             let cpu_results = if cpu_bases.len() > 0 {
                 cpu_bases.par_chunks(per_core_chunk_size)
                     .zip(cpu_exps.par_chunks(per_core_chunk_size))
-                    .zip(cpu_core_ids.par_chunks(per_core_chunk_size))
-                    .map(|((bases, exps), core_ids)| -> Result<<G as CurveAffine>::Projective, GPUError> {
+                    .enumerate()
+                    .map(|(chunk_idx, (bases, exps))| -> Result<<G as CurveAffine>::Projective, GPUError> {
                         let mut acc = <G as CurveAffine>::Projective::zero();
+                        // One core id per chunk, derived from the chunk's own
+                        // index rather than a separately-chunked fixed-size
+                        // `1..64` vec: that vec's length didn't track
+                        // `used_core`/`per_core_chunk_size`, so zipping its
+                        // `par_chunks` against `bases`/`exps`'s silently
+                        // truncated to whichever ran out of elements first
+                        // and dropped the rest of the multiexp's contribution.
+                        let core_ids = vec![chunk_idx % 64];
 
                         {
                             let origin = counter.lock().unwrap();
@@ -306,7 +1525,7 @@ pub fn only_cpu_multiexp<G>(
                             FullDensity,
                             Arc::new(exps.to_vec()),
                             &mut None,
-                            Vec::from(core_ids),
+                            core_ids,
                         );
 
                         {
@@ -339,7 +1558,7 @@ pub fn only_cpu_multiexp<G>(
 // A struct that containts several multiexp kernels for different devices
 pub struct MultiexpKernel<E>
 where
-    E: Engine,
+    E: GlvEngine,
 {
     kernels: Vec<SingleMultiexpKernel<E>>,
     _lock: locks::GPULock, // RFC 1857: struct fields are dropped in the same order as they are declared.
@@ -347,16 +1566,36 @@ where
 
 impl<E> MultiexpKernel<E>
 where
-    E: Engine,
+    E: GlvEngine,
 {
     pub fn create(priority: bool) -> GPUResult<MultiexpKernel<E>> {
+        Self::create_with_priority_handle(priority, PriorityHandle::from_bool(priority))
+    }
+
+    /// Same as `create`, but every device's kernel shares the given
+    /// `PriorityHandle` instead of one derived from `priority`, so the
+    /// caller can `raise`/`cancel` this whole call's priority (across every
+    /// device it's using) while it's in flight.
+    pub fn create_with_priority_handle(
+        priority: bool,
+        priority_handle: PriorityHandle,
+    ) -> GPUResult<MultiexpKernel<E>> {
         let lock = locks::GPULock::lock();
 
         let devices = opencl::Device::all()?;
 
         let kernels: Vec<_> = devices
             .into_iter()
-            .map(|d| (d.clone(), SingleMultiexpKernel::<E>::create(d, priority)))
+            .map(|d| {
+                (
+                    d.clone(),
+                    SingleMultiexpKernel::<E>::create_with_priority_handle(
+                        d,
+                        priority,
+                        priority_handle.clone(),
+                    ),
+                )
+            })
             .filter_map(|(device, res)| {
                 if let Err(ref e) = res {
                     error!(
@@ -391,6 +1630,21 @@ where
         })
     }
 
+    // NOTE: `bases` below is a fully materialized `Arc<Vec<G>>` — the whole
+    // base vector for this call has to already be resident, which is what
+    // makes a multi-GB parameter file dominate memory (see the
+    // `ParameterSource::get_h`/`get_l`/`get_a`/`get_b_g1`/`get_b_g2` fetch in
+    // `groth16::prover`, which eagerly loads each base vector in full before
+    // any multiexp runs). `SingleMultiexpKernel::multiexp_pipelined_from_chunks`
+    // above already lets one device's kernel consume bases/exps from an
+    // on-demand chunk iterator instead of a resident slice; extending that
+    // down to this multi-device entry point and all the way up to
+    // `groth16::prover` still needs the `Source`/`SourceBuilder` trait pair
+    // that `ParameterSource` hands back (the `add_assign_mixed`/`skip`
+    // interface) to grow a memory-mapped or buffered `io::Read` impl. Those
+    // traits live in `crate::multiexp` alongside `ParameterSource` in
+    // `groth16::mod`, outside this GPU-kernel module, so that last wiring
+    // step belongs there, not here.
     pub fn multiexp<G>(
         &mut self,
         pool: &Worker,
@@ -408,7 +1662,7 @@ where
         // https://github.com/zkcrypto/bellman/blob/10c5010fd9c2ca69442dc9775ea271e286e776d8/src/multiexp.rs#L38
         let bases = &bases[skip..(skip + n)];
         let exps = &exps[..n];
-        let cpu_n = ((n as f64) * get_cpu_utilization()) as usize;
+        let cpu_n = ((n as f64) * adaptive_cpu_fraction()) as usize;
         let n = n - cpu_n;
         let (cpu_bases, bases) = bases.split_at(cpu_n);
         let (cpu_exps, exps) = exps.split_at(cpu_n);
@@ -434,14 +1688,14 @@ where
 
             // concurrent computing
             let (tx_gpu, rx_gpu) = mpsc::channel();
-            //let (tx_cpu, rx_cpu) = mpsc::channel();
-            let mut scoped_pool = Pool::new(2);
+            let (tx_cpu, rx_cpu) = mpsc::channel();
 
             let mut gpu_core_ids = vec![core_affinity::CoreId{id: 125 as usize}, core_affinity::CoreId{id: 126 as usize}];
 
             rayon::scope(|scoped| {
                 // GPU
                 scoped.spawn(move |_| {
+                    let gpu_start = Instant::now();
                     let results = if n > 0 {
                         bases
                             .par_chunks(chunk_size)
@@ -453,17 +1707,11 @@ where
                                 info!("ZQ: force set cpu coreID: {:?}", core_id);
                                 info!("ZQ: bases len: {:?}, exps len: {:?}", bases.len(), exps.len());
 
-                                let mut acc = <G as CurveAffine>::Projective::zero();
-                                let jack_chunk_3080 = 33554466;
-                                let mut jack_windows_size = 11;
-                                let size_result = std::mem::size_of::<<G as CurveAffine>::Projective>();
-                                if size_result > 144 {
-                                    jack_windows_size = 8;
-                                }
-                                for (bases, exps) in bases.chunks(jack_chunk_3080).zip(exps.chunks(jack_chunk_3080)) {
-                                    let result = kern.multiexp(bases, exps, bases.len(), jack_windows_size)?;
-                                    acc.add_assign(&result);
-                                }
+                                // Resolved once per device from the on-disk tuning cache (or a
+                                // one-time benchmark sweep if this device hasn't been profiled yet).
+                                let pipeline_chunk_size = kern.tuned_chunk_size(bases, exps)?;
+                                // Double-buffered: chunk i+1's upload overlaps chunk i's kernel.
+                                let acc = kern.multiexp_pipelined(bases, exps, pipeline_chunk_size)?;
 
                                 Ok(acc)
                             })
@@ -472,84 +1720,123 @@ where
                         Vec::new()
                     };
 
+                    if n > 0 {
+                        GPU_THROUGHPUT.update((n as f64) / gpu_start.elapsed().as_secs_f64());
+                    }
                     tx_gpu.send(results).unwrap();
 
                 });
 
-                /*
                 // CPU
-                scoped.execute(move || {
-                    let used_core = 128;
-                    let per_core_chunk_size = ((cpu_bases.len() as f64) / (used_core as f64)).ceil() as usize;
-                    let cpu_results = if cpu_bases.len() > 0 {
-                        cpu_bases.par_chunks(per_core_chunk_size)
-                            .zip(cpu_exps.par_chunks(per_core_chunk_size))
-                            .map(|(bases, exps)| -> Result<<G as CurveAffine>::Projective, GPUError> {
-                                let mut acc = <G as CurveAffine>::Projective::zero();
-
-                                let cpu_waiter = cpu_multiexp(
-                                    &pool,
-                                    (Arc::new(bases.to_vec()), 0),
-                                    FullDensity,
-                                    Arc::new(exps.to_vec()),
-                                    &mut None,
-                                );
-
-                                acc = cpu_waiter.wait().unwrap();
-
-                                Ok(acc)
-                            })
-                            .collect::<Vec<_>>()
+                scoped.spawn(move |_| {
+                    let cpu_start = Instant::now();
+                    let cpu_result = if cpu_bases.len() > 0 {
+                        Some(only_cpu_multiexp(
+                            pool,
+                            Arc::new(cpu_bases.to_vec()),
+                            Arc::new(cpu_exps.to_vec()),
+                            0,
+                            cpu_bases.len(),
+                        ))
                     } else {
-                        Vec::new()
+                        None
                     };
 
-                    tx_cpu.send(cpu_results).unwrap();
-                });
-
-                 */
-
-                /*
-                // CPU
-                scoped.execute(move || {
-                    let cpu_acc = cpu_multiexp(
-                        &pool,
-                        (Arc::new(cpu_bases.to_vec()), 0),
-                        FullDensity,
-                        Arc::new(cpu_exps.to_vec()),
-                        &mut None,
-                    );
-                    let cpu_r = cpu_acc.wait().unwrap();
-
-                    tx_cpu.send(cpu_r).unwrap();
+                    if cpu_n > 0 {
+                        CPU_THROUGHPUT.update((cpu_n as f64) / cpu_start.elapsed().as_secs_f64());
+                    }
+                    tx_cpu.send(cpu_result).unwrap();
                 });
-                 */
             });
 
             // waiting results...
             let gpu_results = rx_gpu.recv().unwrap();
-            //let cpu_results = rx_cpu.recv().unwrap();
+            let cpu_result = rx_cpu.recv().unwrap();
 
             for r in gpu_results {
                 acc.add_assign(&r?);
             }
-
-            //for r in cpu_results {
-            //    acc.add_assign(&r?);
-            //}
-
-            /*
-            // waiting results...
-            let results = rx_gpu.recv().unwrap();
-            let cpu_r = rx_cpu.recv().unwrap();
-
-            for r in results {
-                acc.add_assign(&r?);
+            if let Some(cpu_result) = cpu_result {
+                acc.add_assign(&cpu_result?);
             }
-            acc.add_assign(&cpu_r);
-             */
-            
+
             Ok(acc)
         })
     }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::bls::{Bls12, Fr};
+    use rand_core::SeedableRng;
+    use rand_xorshift::XorShiftRng;
+
+    #[test]
+    fn glv_decompose_round_trip() {
+        let mut rng = XorShiftRng::from_seed([
+            0x59, 0x62, 0xbe, 0x5d, 0x76, 0x3d, 0x31, 0x8d, 0x17, 0xdb, 0x37, 0x32, 0x54, 0x06,
+            0xbc, 0xe5,
+        ]);
+
+        let base = <Bls12 as Engine>::G1Affine::one();
+        let phi_base = <Bls12 as GlvEngine>::glv_endo_g1(&base);
+
+        for _ in 0..64 {
+            let k = Fr::random(&mut rng);
+            let k_repr = k.into_repr();
+
+            let expected = base.mul(k_repr);
+
+            let (k1, sign1, k2, sign2) = glv_decompose::<Fr>(&BLS12_381_GLV, &k_repr);
+            let p1 = if sign1 { base } else { -base };
+            let p2 = if sign2 { phi_base } else { -phi_base };
+            let mut actual = p1.mul(u128_to_repr::<<Bls12 as Engine>::G1Affine>(k1));
+            actual.add_assign(&p2.mul(u128_to_repr::<<Bls12 as Engine>::G1Affine>(k2)));
+
+            assert_eq!(
+                expected.into_affine(),
+                actual.into_affine(),
+                "k*P != k1*P + k2*phi(P) for k = {:?}",
+                k_repr
+            );
+        }
+    }
+
+    /// Same round trip as `glv_decompose_round_trip`, but for G2: `glv_expand`/
+    /// `glv_decompose` are generic over both curves, and the real multiexp
+    /// path (`MultiexpKernel::multiexp` over `E::G2Affine`) exercises the G2
+    /// endomorphism, so G1 coverage alone doesn't catch a sign/lattice bug
+    /// that's specific to `glv_endo_g2`.
+    #[test]
+    fn glv_decompose_round_trip_g2() {
+        let mut rng = XorShiftRng::from_seed([
+            0x59, 0x62, 0xbe, 0x5d, 0x76, 0x3d, 0x31, 0x8d, 0x17, 0xdb, 0x37, 0x32, 0x54, 0x06,
+            0xbc, 0xe5,
+        ]);
+
+        let base = <Bls12 as Engine>::G2Affine::one();
+        let phi_base = <Bls12 as GlvEngine>::glv_endo_g2(&base);
+
+        for _ in 0..64 {
+            let k = Fr::random(&mut rng);
+            let k_repr = k.into_repr();
+
+            let expected = base.mul(k_repr);
+
+            let (k1, sign1, k2, sign2) = glv_decompose::<Fr>(&BLS12_381_GLV, &k_repr);
+            let p1 = if sign1 { base } else { -base };
+            let p2 = if sign2 { phi_base } else { -phi_base };
+            let mut actual = p1.mul(u128_to_repr::<<Bls12 as Engine>::G2Affine>(k1));
+            actual.add_assign(&p2.mul(u128_to_repr::<<Bls12 as Engine>::G2Affine>(k2)));
+
+            assert_eq!(
+                expected.into_affine(),
+                actual.into_affine(),
+                "k*P != k1*P + k2*phi(P) for k = {:?} (G2)",
+                k_repr
+            );
+        }
+    }
 }
\ No newline at end of file
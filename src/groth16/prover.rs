@@ -11,13 +11,13 @@ use rayon::prelude::*;
 
 use super::{ParameterSource, Proof};
 use crate::domain::{EvaluationDomain, Scalar};
-use crate::gpu::{LockedFFTKernel, LockedMultiexpKernel, LockedFFTKernel_1};
+use crate::gpu::{LockedFFTKernel, LockedMultiexpKernel, LockedFFTKernel_1, device_count, devices, SingleMultiexpKernel};
 use crate::multicore::{Worker, THREAD_POOL, Waiter};
 use crate::multiexp::{multiexp, multiexp_fulldensity, multiexp_fulldensity_only_cpu, density_filter, multiexp_skipdensity, DensityTracker, FullDensity, SourceBuilder};
 use crate::{
     Circuit, ConstraintSystem, Index, LinearCombination, SynthesisError, Variable, BELLMAN_VERSION,
 };
-use log::info;
+use log::{error, info};
 
 // use crossbeam_channel::{bounded, Receiver};
 
@@ -280,7 +280,74 @@ where
 {
     info!("Bellperson {} is being used!", BELLMAN_VERSION);
 
-    THREAD_POOL.install(|| create_proof_batch_priority_inner(circuits, params, r_s, s_s, priority))
+    // No caller-held handle to preempt this call with, so derive a
+    // fresh one-off handle from the bool the same way
+    // `SingleMultiexpKernel::create` does for callers that don't care.
+    let priority_handle = crate::gpu::PriorityHandle::from_bool(priority);
+    THREAD_POOL.install(|| {
+        create_proof_batch_priority_inner(circuits, params, r_s, s_s, priority, priority_handle)
+    })
+}
+
+/// Same as `create_random_proof_batch_priority`, but takes a
+/// `gpu::PriorityHandle` the caller keeps a clone of (e.g. to hand off to
+/// another thread) instead of a plain `priority: bool`. The handle can be
+/// `raise`d or `cancel`ed while this call is still running: every multiexp
+/// kernel created below is handed the *same* handle (cloned, not re-derived
+/// from a bool), so the GPU multiexp stages check the handle's live level
+/// between chunks (see `gpu::PriorityHandle::should_yield`) and a background
+/// batch can be preempted mid-proof by a later, higher-priority caller
+/// instead of only losing the GPU at its *next* call, which is all the
+/// plain `bool` flag could ever express. FFT kernels (`LockedFFTKernel`/
+/// `LockedFFTKernel_1`) are not yet on this path -- they still only take
+/// the plain `bool` snapshotted below, so a proof can still only be
+/// preempted during its multiexp stages, not its FFT stages. Wiring FFT
+/// preemption through is still open.
+pub fn create_random_proof_batch_priority_with_handle<E, C, R, P: ParameterSource<E>>(
+    circuits: Vec<C>,
+    params: P,
+    rng: &mut R,
+    priority_handle: crate::gpu::PriorityHandle,
+) -> Result<Vec<Proof<E>>, SynthesisError>
+where
+    E: Engine,
+    C: Circuit<E> + Send,
+    R: RngCore,
+{
+    let r_s = (0..circuits.len()).map(|_| E::Fr::random(rng)).collect();
+    let s_s = (0..circuits.len()).map(|_| E::Fr::random(rng)).collect();
+
+    create_proof_batch_priority_with_handle::<E, C, P>(circuits, params, r_s, s_s, priority_handle)
+}
+
+/// Same as `create_proof_batch_priority`; see
+/// `create_random_proof_batch_priority_with_handle` for why a caller would
+/// reach for this instead.
+pub fn create_proof_batch_priority_with_handle<E, C, P: ParameterSource<E>>(
+    circuits: Vec<C>,
+    params: P,
+    r_s: Vec<E::Fr>,
+    s_s: Vec<E::Fr>,
+    priority_handle: crate::gpu::PriorityHandle,
+) -> Result<Vec<Proof<E>>, SynthesisError>
+where
+    E: Engine,
+    C: Circuit<E> + Send,
+{
+    info!("Bellperson {} is being used!", BELLMAN_VERSION);
+
+    // `LockedFFTKernel`/`LockedFFTKernel_1` still only take a plain `bool`
+    // today (see the doc comment above), so this is the one-time snapshot
+    // handed down to them. The multiexp stages get the real
+    // `priority_handle` itself, not this snapshot -- it's threaded through
+    // `create_proof_batch_priority_inner` and cloned into every per-device
+    // kernel, so `raise`/`cancel` on *this* handle actually reaches the
+    // kernels the caller is holding a reference to.
+    let priority = priority_handle.level() > crate::gpu::BACKGROUND_PRIORITY;
+
+    THREAD_POOL.install(|| {
+        create_proof_batch_priority_inner(circuits, params, r_s, s_s, priority, priority_handle)
+    })
 }
 
 /*
@@ -289,12 +356,31 @@ fn print_type_of<T>(_: &T) -> String {
 }
  */
 
+// Builds the constraint-system assignment for a single circuit: allocates
+// the "one" input, runs the circuit's own synthesize logic, then adds the
+// input-consistency constraints the prover relies on. Shared by the eager
+// batch path and the streaming path below.
+fn synthesize_one<E, C>(circuit: C) -> Result<ProvingAssignment<E>, SynthesisError>
+where
+    E: Engine,
+    C: Circuit<E>,
+{
+    let mut prover = ProvingAssignment::new();
+    prover.alloc_input(|| "", || Ok(E::Fr::one()))?;
+    circuit.synthesize(&mut prover)?;
+    for i in 0..prover.input_assignment.len() {
+        prover.enforce(|| "", |lc| lc + Variable(Index::Input(i)), |lc| lc, |lc| lc);
+    }
+    Ok(prover)
+}
+
 fn create_proof_batch_priority_inner<E, C, P: ParameterSource<E>>(
     circuits: Vec<C>,
     params: P,
     r_s: Vec<E::Fr>,
     s_s: Vec<E::Fr>,
     priority: bool,
+    priority_handle: crate::gpu::PriorityHandle,
 ) -> Result<Vec<Proof<E>>, SynthesisError>
 where
     E: Engine,
@@ -309,15 +395,7 @@ where
     // 生成电路的约束系统，这一步会回调每个电路不同的电路逻辑
     let mut provers = circuits
         .into_par_iter()
-        .map(|circuit| -> Result<_, SynthesisError> {
-            let mut prover = ProvingAssignment::new();
-            prover.alloc_input(|| "", || Ok(E::Fr::one()))?;
-            circuit.synthesize(&mut prover)?;
-            for i in 0..prover.input_assignment.len() {
-                prover.enforce(|| "", |lc| lc + Variable(Index::Input(i)), |lc| lc, |lc| lc);
-            }
-            Ok(prover)
-        })
+        .map(synthesize_one::<E, C>)
         .collect::<Result<Vec<_>, _>>()?;
     info!("ZQ: build provers end: {:?}", now.elapsed());
 
@@ -437,58 +515,110 @@ where
     info!("ZQ: a_s provers length: {:?}", provers.len());
     // iFFT生成三个多项式系数，cosetFFT生成多项式在coset处的取值.
     let now = Instant::now();
-    let mut fft_kern = Some(LockedFFTKernel::<E>::new(log_d, priority));
-    let mut fft_kern_1 = Some(LockedFFTKernel_1::<E>::new(log_d, priority));
-    let mut pool = Pool::new(3);
-    let a_s = provers
-        .iter_mut()
-        .map(|prover| {
-            let mut a =
-                EvaluationDomain::from_coeffs(std::mem::replace(&mut prover.a, Vec::new()))?;
-            let mut b =
-                EvaluationDomain::from_coeffs(std::mem::replace(&mut prover.b, Vec::new()))?;
-            let mut c =
-                EvaluationDomain::from_coeffs(std::mem::replace(&mut prover.c, Vec::new()))?;
-
-            let now = Instant::now();
-            pool.scoped(|scoped| {
-
-                scoped.execute( || {
-                    a.ifft(&worker, &mut fft_kern).unwrap();
-                    a.coset_fft(&worker, &mut fft_kern).unwrap();
-                });
-
-                scoped.execute( || {
-                    b.ifft_1(&worker, &mut fft_kern_1).unwrap();
-                    b.coset_fft_1(&worker, &mut fft_kern_1).unwrap();
-                });
 
+    // Hand circuits out to one worker thread per detected GPU instead of
+    // funnelling the whole batch through a single shared FFT kernel pair.
+    // Each worker keeps its own `LockedFFTKernel`/`LockedFFTKernel_1` alive
+    // for the whole call, so kernel setup only happens once per device.
+    let num_fft_devices = device_count();
+    info!("ZQ: a_s using {} device worker(s)", num_fft_devices);
+
+    let (a_job_tx, a_job_rx) = mpsc::channel();
+    for (idx, prover) in provers.iter_mut().enumerate() {
+        let a = EvaluationDomain::from_coeffs(std::mem::replace(&mut prover.a, Vec::new()))?;
+        let b = EvaluationDomain::from_coeffs(std::mem::replace(&mut prover.b, Vec::new()))?;
+        let c = EvaluationDomain::from_coeffs(std::mem::replace(&mut prover.c, Vec::new()))?;
+        a_job_tx.send((idx, a, b, c)).unwrap();
+    }
+    drop(a_job_tx);
+    let a_job_rx = std::sync::Mutex::new(a_job_rx);
+    let a_job_rx = &a_job_rx;
+
+    let (a_s_tx, a_s_rx) = mpsc::channel();
+    let mut device_pool = Pool::new(num_fft_devices as u32);
+    device_pool.scoped(|scoped| {
+        for device_idx in 0..num_fft_devices {
+            let worker = worker.clone();
+            let a_s_tx = a_s_tx.clone();
+
+            scoped.execute(move || {
+                let mut fft_kern = Some(LockedFFTKernel::<E>::new(log_d, priority));
+                let mut fft_kern_1 = Some(LockedFFTKernel_1::<E>::new(log_d, priority));
+                let mut pool = Pool::new(3);
+
+                loop {
+                    let job = a_job_rx.lock().unwrap().recv();
+                    let (idx, mut a, mut b, mut c) = match job {
+                        Ok(job) => job,
+                        Err(_) => break,
+                    };
+                    info!("ZQ: a_s device {:?} round: {:?}", device_idx, idx);
+
+                    // A GPU error here (e.g. `GPUError::GPUTaken` from a
+                    // higher-priority job preempting this one) must surface
+                    // as a `SynthesisError` to the caller, not panic this
+                    // worker thread, so the whole fallible sequence runs
+                    // inside one `Result`-returning closure and is sent
+                    // through the channel instead of `.unwrap()`ed in place.
+                    let result = (|| -> Result<Arc<Vec<_>>, SynthesisError> {
+                        let now = Instant::now();
+                        let mut a_res: Result<(), SynthesisError> = Ok(());
+                        let mut b_res: Result<(), SynthesisError> = Ok(());
+                        pool.scoped(|scoped| {
+
+                            scoped.execute(|| {
+                                a_res = (|| -> Result<(), SynthesisError> {
+                                    a.ifft(&worker, &mut fft_kern)?;
+                                    a.coset_fft(&worker, &mut fft_kern)?;
+                                    Ok(())
+                                })();
+                            });
+
+                            scoped.execute(|| {
+                                b_res = (|| -> Result<(), SynthesisError> {
+                                    b.ifft_1(&worker, &mut fft_kern_1)?;
+                                    b.coset_fft_1(&worker, &mut fft_kern_1)?;
+                                    Ok(())
+                                })();
+                            });
+
+                        });
+                        a_res?;
+                        b_res?;
+
+                        c.ifft(&worker, &mut fft_kern)?;
+                        c.coset_fft(&worker, &mut fft_kern)?;
+
+                        info!("ZQ: a_s phase 1 duration: {:?}", now.elapsed());
+
+
+                        // a * b - c / z
+                        a.mul_assign(&worker, &b);
+                        drop(b);
+                        a.sub_assign(&worker, &c);
+                        drop(c);
+                        a.divide_by_z_on_coset(&worker);
+                        a.icoset_fft(&worker, &mut fft_kern)?;
+
+                        let mut a = a.into_coeffs();
+                        let a_len = a.len() - 1;
+                        a.truncate(a_len);
+
+                        Ok(Arc::new(a.into_par_iter().map(|s| s.0.into_repr()).collect::<Vec<_>>()))
+                    })();
+                    a_s_tx.send((idx, result)).unwrap();
+                }
             });
+        }
+    });
+    drop(a_s_tx);
 
-            c.ifft(&worker, &mut fft_kern).unwrap();
-            c.coset_fft(&worker, &mut fft_kern).unwrap();
-
-            info!("ZQ: a_s phase 1 duration: {:?}", now.elapsed());
-
-
-            // a * b - c / z
-            a.mul_assign(&worker, &b);
-            drop(b);
-            a.sub_assign(&worker, &c);
-            drop(c);
-            a.divide_by_z_on_coset(&worker);
-            a.icoset_fft(&worker, &mut fft_kern)?;
-
-            let mut a = a.into_coeffs();
-            let a_len = a.len() - 1;
-            a.truncate(a_len);
-
-            Ok(Arc::new(a.into_par_iter().map(|s| s.0.into_repr()).collect::<Vec<_>>()))
-        })
-        .collect::<Result<Vec<_>, SynthesisError>>()?;
+    let mut a_s: Vec<Option<_>> = (0..provers.len()).map(|_| None).collect();
+    for (idx, a) in a_s_rx.iter() {
+        a_s[idx] = Some(a?);
+    }
+    let a_s: Vec<_> = a_s.into_iter().map(|a| a.unwrap()).collect();
     info!("ZQ: a_s end: {:?}", now.elapsed());
-    drop(fft_kern);
-    drop(fft_kern_1);
 
     /*
     info!("ZQ: h_s start");
@@ -516,12 +646,21 @@ where
     info!("ZQ h_s start");
     // 把之前计算的数（多项式值），映射到椭圆曲线上。
 
-    let percent = 2;
-    let cpu_a_s = &a_s[0..percent];
-    let gpu_a_s = &a_s[percent..];
+    // Circuits are handed out one at a time from a shared counter instead of
+    // a fixed CPU/GPU split, so whichever path is actually faster on this
+    // machine ends up claiming more of the batch. One GPU worker is spun up
+    // per detected device, each bound to its own `SingleMultiexpKernel` for
+    // the whole stage (not a `LockedMultiexpKernel`, which would pull every
+    // device in under one shared `GPULock` instead of just this worker's),
+    // so a multi-GPU box keeps every device busy instead of funnelling all
+    // of them through a single kernel.
+    let h_s_next = std::sync::atomic::AtomicUsize::new(0);
+    let a_s_ref = &a_s;
+    let h_s_devices = devices().unwrap_or_default();
+    let num_h_s_devices = h_s_devices.len();
 
     use scoped_threadpool::Pool;
-    let mut cpu_gpu_pool = Pool::new(2);
+    let mut cpu_gpu_pool = Pool::new(1 + num_h_s_devices as u32);
 
     let (h_s_tx_cpu, h_s_rx_cpu) = mpsc::channel();
     let (h_s_tx_gpu, h_s_rx_gpu) = mpsc::channel();
@@ -529,83 +668,82 @@ where
     cpu_gpu_pool.scoped(|scoped| {
         let worker_cpu = worker.clone();
         let params_cpu = h_params.clone();
+        let h_s_next_cpu = &h_s_next;
 
-        // cpu work list
+        // cpu worker
         scoped.execute(move || {
             let h_s_cpu_start = Instant::now();
             info!("ZQ h_s cpu start");
-            
-            let first = cpu_a_s.get(0).unwrap().clone();
-            let result = multiexp_fulldensity_only_cpu(
-                &worker_cpu,
-                params_cpu.clone(),
-                FullDensity,
-                first);
-            h_s_tx_cpu.send(result).unwrap();
-
-            info!("ZQ h_s cpu round 1 end");
 
-            let first = cpu_a_s.get(1).unwrap().clone();
-            let result = multiexp_fulldensity_only_cpu(
-                &worker_cpu,
-                params_cpu.clone(),
-                FullDensity,
-                first);
-            h_s_tx_cpu.send(result).unwrap();
+            loop {
+                let idx = h_s_next_cpu.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+                let a = match a_s_ref.get(idx) {
+                    Some(a) => a.clone(),
+                    None => break,
+                };
+                info!("ZQ h_s cpu round: {:?}", idx);
+                let result = multiexp_fulldensity_only_cpu(
+                    &worker_cpu,
+                    params_cpu.clone(),
+                    FullDensity,
+                    a);
+                h_s_tx_cpu.send((idx, result)).unwrap();
+            }
 
-            info!("ZQ h_s cpu round 2 end");
             info!("ZQ h_s cpu end: {:?}", h_s_cpu_start.elapsed());
         });
 
-        let worker_gpu = worker.clone();
-        let mut params_gpu = h_params.clone();
-        // gpu work list
-        scoped.execute(move || {
-            let h_s_gpu_start = Instant::now();
-            info!("ZQ h_s gpu start");
-            let mut i = 1;
-
-            let mut multiexp_kern = Some(LockedMultiexpKernel::<E>::new(log_d, priority));
-
-            let mut gpu_result_list = gpu_a_s
-                .into_iter()
-                .map(|a| {
-                    info!("ZQ h_s gpu round: {:?}", i);
+        // one gpu worker per device, each bound to its own kernel instance
+        for (device_idx, device) in h_s_devices.iter().cloned().enumerate() {
+            let worker_gpu = worker.clone();
+            let params_gpu = h_params.clone();
+            let h_s_next_gpu = &h_s_next;
+            let h_s_tx_gpu = h_s_tx_gpu.clone();
+            let priority_handle = priority_handle.clone();
+
+            scoped.execute(move || {
+                let h_s_gpu_start = Instant::now();
+                info!("ZQ h_s gpu {:?} start", device_idx);
+
+                let mut multiexp_kern = match SingleMultiexpKernel::<E>::create_with_priority_handle(device, priority, priority_handle) {
+                    Ok(kernel) => Some(kernel),
+                    Err(e) => {
+                        error!("Cannot initialize h_s multiexp kernel for device {}: {}", device_idx, e);
+                        None
+                    }
+                };
+
+                loop {
+                    let idx = h_s_next_gpu.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+                    let a = match a_s_ref.get(idx) {
+                        Some(a) => a.clone(),
+                        None => break,
+                    };
+                    info!("ZQ h_s gpu {:?} round: {:?}", device_idx, idx);
                     let h = multiexp_fulldensity(
                         &worker_gpu,
                         params_gpu.clone(),
                         FullDensity,
-                        a.clone(),
+                        a,
                         &mut multiexp_kern,
                     );
-                    i += 1;
-                    Ok(h)
-                })
-                .collect::<Result<Vec<_>, SynthesisError>>();
-
-            if let Ok(result_list) = gpu_result_list {
-                for item in result_list {
-                    h_s_tx_gpu.send(item.wait()).unwrap();
+                    h_s_tx_gpu.send((idx, h.wait())).unwrap();
                 }
-            }
 
-            info!("ZQ h_s gpu end: {:?}", h_s_gpu_start.elapsed());
-        });
+                info!("ZQ h_s gpu {:?} end: {:?}", device_idx, h_s_gpu_start.elapsed());
+            });
+        }
+        drop(h_s_tx_gpu);
     });
 
-    let mut h_s = Vec::new();
-    for result in h_s_rx_cpu.recv() {
-        h_s.push(Waiter::done(result));
-    }
-
-    for result in h_s_rx_gpu.recv() {
-        h_s.push(Waiter::done(result));
+    let mut h_s: Vec<Option<_>> = (0..a_s.len()).map(|_| None).collect();
+    for (idx, result) in h_s_rx_cpu.iter().chain(h_s_rx_gpu.iter()) {
+        h_s[idx] = Some(Waiter::done(result));
     }
+    let h_s: Vec<_> = h_s.into_iter().map(|h| h.unwrap()).collect();
 
     info!("ZQ h_s end: {:?}", h_s_start.elapsed());
 
-    let mut multiexp_kern = Some(LockedMultiexpKernel::<E>::new(log_d, priority));
-
     /*
     let first = first_as.get(0).unwrap().clone();
     let result = multiexp_fulldensity_only_cpu(
@@ -640,86 +778,92 @@ where
     let l_s_start = Instant::now();
     info!("ZQ l_s start");
 
-    let percent = 2;
-    let cpu_l_s = &assignments[0..percent];
-    let gpu_l_s = &assignments[percent..];
-    let cpu_l_s = cpu_l_s.to_vec();
+    // Same shared-counter balancing as h_s above: each circuit's aux
+    // assignment is handed to whichever of CPU/GPU asks for work next, with
+    // one GPU worker per detected device, each bound to its own
+    // `SingleMultiexpKernel` instance.
+    let l_s_next = std::sync::atomic::AtomicUsize::new(0);
+    let assignments_ref = &assignments;
+    let l_s_devices = devices().unwrap_or_default();
+    let num_l_s_devices = l_s_devices.len();
 
-    let mut cpu_gpu_pool = Pool::new(2);
+    let mut cpu_gpu_pool = Pool::new(1 + num_l_s_devices as u32);
 
     let (l_s_tx_cpu, l_s_rx_cpu) = mpsc::channel();
     let (l_s_tx_gpu, l_s_rx_gpu) = mpsc::channel();
 
-    let worker_cpu = worker.clone();
-    let params_cpu = l_params.clone();
     cpu_gpu_pool.scoped(|scoped| {
         let worker_cpu = worker.clone();
         let params_cpu = l_params.clone();
+        let l_s_next_cpu = &l_s_next;
 
         info!("ZQ l_s cpu start");
         scoped.execute(move || {
-            let (_, first) = cpu_l_s.get(0).unwrap().clone();
-            let result = multiexp_fulldensity_only_cpu(
-                &worker_cpu,
-                params_cpu.clone(),
-                FullDensity,
-                first);
-
-            let (_, second) = cpu_l_s.get(1).unwrap().clone();
-            let result = multiexp_fulldensity_only_cpu(
-                &worker_cpu,
-                params_cpu.clone(),
-                FullDensity,
-                second);
-
-            l_s_tx_cpu.send(result).unwrap();
+            loop {
+                let idx = l_s_next_cpu.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+                let aux_assignment = match assignments_ref.get(idx) {
+                    Some((_, aux_assignment)) => aux_assignment.clone(),
+                    None => break,
+                };
+                info!("ZQ l_s cpu round: {:?}", idx);
+                let result = multiexp_fulldensity_only_cpu(
+                    &worker_cpu,
+                    params_cpu.clone(),
+                    FullDensity,
+                    aux_assignment);
+                l_s_tx_cpu.send((idx, result)).unwrap();
+            }
         });
 
-        let worker_gpu = worker.clone();
-        let mut params_gpu = l_params.clone();
-        scoped.execute(move || {
-            let h_s_gpu_start = Instant::now();
-            info!("ZQ l_s gpu start");
-            let mut i = 1;
-
-            let mut multiexp_kern = Some(LockedMultiexpKernel::<E>::new(log_d, priority));
-
-            let mut gpu_result_list = gpu_l_s
-                .into_iter()
-                .map(|(_, aux_assignment)| {
-                    info!("ZQ l_s gpu round: {:?}", i);
+        for (device_idx, device) in l_s_devices.iter().cloned().enumerate() {
+            let worker_gpu = worker.clone();
+            let params_gpu = l_params.clone();
+            let l_s_next_gpu = &l_s_next;
+            let l_s_tx_gpu = l_s_tx_gpu.clone();
+            let priority_handle = priority_handle.clone();
+
+            scoped.execute(move || {
+                let l_s_gpu_start = Instant::now();
+                info!("ZQ l_s gpu {:?} start", device_idx);
+
+                let mut multiexp_kern = match SingleMultiexpKernel::<E>::create_with_priority_handle(device, priority, priority_handle) {
+                    Ok(kernel) => Some(kernel),
+                    Err(e) => {
+                        error!("Cannot initialize l_s multiexp kernel for device {}: {}", device_idx, e);
+                        None
+                    }
+                };
+
+                loop {
+                    let idx = l_s_next_gpu.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+                    let aux_assignment = match assignments_ref.get(idx) {
+                        Some((_, aux_assignment)) => aux_assignment.clone(),
+                        None => break,
+                    };
+                    info!("ZQ l_s gpu {:?} round: {:?}", device_idx, idx);
                     let h = multiexp_fulldensity(
                         &worker_gpu,
                         params_gpu.clone(),
                         FullDensity,
-                        aux_assignment.clone(),
+                        aux_assignment,
                         &mut multiexp_kern,
                     );
-                    i += 1;
-                    Ok(h)
-                })
-                .collect::<Result<Vec<_>, SynthesisError>>();
-
-            if let Ok(result_list) = gpu_result_list {
-                for item in result_list {
-                    l_s_tx_gpu.send(item.wait()).unwrap();
+                    l_s_tx_gpu.send((idx, h.wait())).unwrap();
                 }
-            }
 
-            info!("ZQ l_s gpu end: {:?}", h_s_gpu_start.elapsed());
-        });
+                info!("ZQ l_s gpu {:?} end: {:?}", device_idx, l_s_gpu_start.elapsed());
+            });
+        }
+        drop(l_s_tx_gpu);
     });
 
-    let mut l_s = Vec::new();
-    for result in l_s_rx_cpu.recv() {
-        l_s.push(Waiter::done(result));
+    let mut l_s: Vec<Option<_>> = (0..assignments.len()).map(|_| None).collect();
+    for (idx, result) in l_s_rx_cpu.iter().chain(l_s_rx_gpu.iter()) {
+        l_s[idx] = Some(Waiter::done(result));
     }
+    let l_s: Vec<_> = l_s.into_iter().map(|l| l.unwrap()).collect();
 
-    for result in l_s_rx_gpu.recv() {
-        l_s.push(Waiter::done(result));
-    }
-
-    info!("ZQ l_s end: {:?}", h_s_start.elapsed());
+    info!("ZQ l_s end: {:?}", l_s_start.elapsed());
 
     /*
     info!("ZQ: l_s start");
@@ -746,120 +890,209 @@ where
     info!("ZQ: inputs start");
     info!("ZQ: inputs length: {:?}", provers.len());
     // 处理电路的公开和私有输入
+    //
+    // NOTE on fusing this stage's six per-circuit multiexps into one
+    // cross-batch call per (base, G1/G2) category: the bases for a_inputs/
+    // b_g1_inputs/b_g2_inputs really are shared across every circuit here
+    // (`a_inputs_source.clone()` etc. below is the same source for the
+    // whole batch), so sharing the base upload across circuits is the
+    // correct idea, and `SingleMultiexpKernel::multiexp_batch_shared_bases`
+    // (gpu::multiexp) implements exactly that: upload `bases` once, reuse
+    // the buffer across one kernel launch per circuit's scalar vector.
+    //
+    // It has no call site here, though, and can't get one without a change
+    // outside this checkout: `multiexp_fulldensity`/`multiexp_skipdensity`
+    // (`crate::multiexp`) own density filtering and the actual upload for
+    // every call below, and the only thing they're handed is a
+    // `SourceBuilder`-derived source plus an assignment - never a resolved
+    // `Vec<G>` of bases. That source's `Source` trait (also
+    // `crate::multiexp`, also not part of this checkout) only exposes
+    // `add_assign_mixed`/`skip` against a caller-owned accumulator: it
+    // streams each base straight into a running sum by design and never
+    // hands the base back out, so there is no way to materialize the `&[G]`
+    // `multiexp_batch_shared_bases` needs from a `ParameterSource`-backed
+    // source without changing that trait or adding a raw-base accessor to
+    // `crate::multiexp`. The deeper optimization the request also gestures
+    // at -- one GPU call whose bucket sums are sliced back out per circuit,
+    // avoiding N separate kernel launches entirely -- would need the
+    // bucket-accumulation step inside the OpenCL kernel to become
+    // segment-aware too (today `SingleMultiexpKernel::launch_kernel`/
+    // `collect_result` always reduce the whole `n` to one accumulator),
+    // which is a `gpu::sources` change, also not part of this checkout.
+    // Both remain blocked on code outside this checkout; this stage stays
+    // the existing per-circuit, per-device-worker loop below.
+    //
+    // Same per-device worker pattern as a_s/h_s/l_s: circuits are queued up
+    // and each device worker pulls the next one, binding its own
+    // `SingleMultiexpKernel` (not a `LockedMultiexpKernel`, which would pull
+    // in every device under one shared `GPULock`) for all six multiexps of
+    // every circuit it handles, for the lifetime of this stage.
     let now = Instant::now();
-    let inputs = provers
-        .into_iter()
-        .zip(assignments.into_iter())
-        .map(|(prover, (input_assignment,aux_assignment))| {
-            let b_input_density = Arc::new(prover.b_input_density);
-            let b_aux_density = Arc::new(prover.b_aux_density);
-
-            let start = Instant::now();
-            let a_inputs = multiexp_fulldensity(
-                &worker,
-                a_inputs_source.clone(),
-                FullDensity,
-                input_assignment.clone(),
-                &mut multiexp_kern,
-            );
-            info!("ZQ: inputs phase 1: {:?}", start.elapsed());
-
-            let start = Instant::now();
-            let (
-                a_aux_bss,
-                a_aux_exps,
-                a_aux_skip,
-                a_aux_n
-            ) = density_filter(
-                a_aux_source.clone(),
-                Arc::new(prover.a_aux_density),
-                aux_assignment.clone()
-            );
-            let a_aux = multiexp_skipdensity(
-                &worker,
-                a_aux_bss,
-                a_aux_exps,
-                a_aux_skip,
-                a_aux_n,
-                &mut multiexp_kern,
-            );
-            info!("ZQ: inputs phase 2: {:?}", start.elapsed());
-
-            let start = Instant::now();
-            let b_g1_inputs = multiexp(
-                &worker,
-                b_g1_inputs_source.clone(),
-                b_input_density.clone(),
-                input_assignment.clone(),
-                &mut multiexp_kern,
-            );
-            info!("ZQ: inputs phase 3: {:?}", start.elapsed());
-
-            let start = Instant::now();
-            let (
-                b_g1_aux_bss,
-                b_g1_aux_exps,
-                b_g1_aux_skip,
-                b_g1_aux_n
-            ) = density_filter(
-                b_g1_aux_source.clone(),
-                b_aux_density.clone(),
-                aux_assignment.clone()
-            );
-            let b_g1_aux = multiexp_skipdensity(
-                &worker,
-                b_g1_aux_bss,
-                b_g1_aux_exps,
-                b_g1_aux_skip,
-                b_g1_aux_n,
-                &mut multiexp_kern,
-            );
-            info!("ZQ: inputs phase 4: {:?}", start.elapsed());
+    let input_devices = devices().unwrap_or_default();
+    let num_input_devices = device_count();
+    info!("ZQ: inputs using {} device worker(s)", num_input_devices);
+
+    let n_provers = provers.len();
+    let (input_job_tx, input_job_rx) = mpsc::channel();
+    for job in provers.into_iter().zip(assignments.into_iter()).enumerate() {
+        input_job_tx.send(job).unwrap();
+    }
+    drop(input_job_tx);
+    let input_job_rx = std::sync::Mutex::new(input_job_rx);
+    let input_job_rx = &input_job_rx;
+
+    let (inputs_tx, inputs_rx) = mpsc::channel();
+    let mut input_device_pool = Pool::new(num_input_devices as u32);
+    input_device_pool.scoped(|scoped| {
+        for device_idx in 0..num_input_devices {
+            let worker = worker.clone();
+            let device = input_devices.get(device_idx).cloned();
+            let a_inputs_source = a_inputs_source.clone();
+            let a_aux_source = a_aux_source.clone();
+            let b_g1_inputs_source = b_g1_inputs_source.clone();
+            let b_g1_aux_source = b_g1_aux_source.clone();
+            let b_g2_inputs_source = b_g2_inputs_source.clone();
+            let b_g2_aux_source = b_g2_aux_source.clone();
+            let inputs_tx = inputs_tx.clone();
+            let priority_handle = priority_handle.clone();
+
+            scoped.execute(move || {
+                let mut multiexp_kern = match device {
+                    Some(device) => match SingleMultiexpKernel::<E>::create_with_priority_handle(device, priority, priority_handle) {
+                        Ok(kernel) => Some(kernel),
+                        Err(e) => {
+                            error!("Cannot initialize inputs multiexp kernel for device {}: {}", device_idx, e);
+                            None
+                        }
+                    },
+                    None => None,
+                };
+
+                loop {
+                    let job = input_job_rx.lock().unwrap().recv();
+                    let (idx, (prover, (input_assignment, aux_assignment))) = match job {
+                        Ok(job) => job,
+                        Err(_) => break,
+                    };
+                    info!("ZQ: inputs device {:?} round: {:?}", device_idx, idx);
+
+                    let b_input_density = Arc::new(prover.b_input_density);
+                    let b_aux_density = Arc::new(prover.b_aux_density);
+
+                    let start = Instant::now();
+                    let a_inputs = multiexp_fulldensity(
+                        &worker,
+                        a_inputs_source.clone(),
+                        FullDensity,
+                        input_assignment.clone(),
+                        &mut multiexp_kern,
+                    );
+                    info!("ZQ: inputs phase 1: {:?}", start.elapsed());
+
+                    let start = Instant::now();
+                    let (
+                        a_aux_bss,
+                        a_aux_exps,
+                        a_aux_skip,
+                        a_aux_n
+                    ) = density_filter(
+                        a_aux_source.clone(),
+                        Arc::new(prover.a_aux_density),
+                        aux_assignment.clone()
+                    );
+                    let a_aux = multiexp_skipdensity(
+                        &worker,
+                        a_aux_bss,
+                        a_aux_exps,
+                        a_aux_skip,
+                        a_aux_n,
+                        &mut multiexp_kern,
+                    );
+                    info!("ZQ: inputs phase 2: {:?}", start.elapsed());
+
+                    let start = Instant::now();
+                    let b_g1_inputs = multiexp(
+                        &worker,
+                        b_g1_inputs_source.clone(),
+                        b_input_density.clone(),
+                        input_assignment.clone(),
+                        &mut multiexp_kern,
+                    );
+                    info!("ZQ: inputs phase 3: {:?}", start.elapsed());
+
+                    let start = Instant::now();
+                    let (
+                        b_g1_aux_bss,
+                        b_g1_aux_exps,
+                        b_g1_aux_skip,
+                        b_g1_aux_n
+                    ) = density_filter(
+                        b_g1_aux_source.clone(),
+                        b_aux_density.clone(),
+                        aux_assignment.clone()
+                    );
+                    let b_g1_aux = multiexp_skipdensity(
+                        &worker,
+                        b_g1_aux_bss,
+                        b_g1_aux_exps,
+                        b_g1_aux_skip,
+                        b_g1_aux_n,
+                        &mut multiexp_kern,
+                    );
+                    info!("ZQ: inputs phase 4: {:?}", start.elapsed());
+
+                    let start = Instant::now();
+                    let b_g2_inputs = multiexp(
+                        &worker,
+                        b_g2_inputs_source.clone(),
+                        b_input_density.clone(),
+                        input_assignment.clone(),
+                        &mut multiexp_kern,
+                    );
+                    info!("ZQ: inputs phase 5: {:?}", start.elapsed());
+
+                    let start = Instant::now();
+                    let (
+                        b_g2_aux_bss,
+                        b_g2_aux_exps,
+                        b_g2_aux_skip,
+                        b_g2_aux_n
+                    ) = density_filter(
+                        b_g2_aux_source.clone(),
+                        b_aux_density.clone(),
+                        aux_assignment.clone()
+                    );
+                    let b_g2_aux = multiexp_skipdensity(
+                        &worker,
+                        b_g2_aux_bss,
+                        b_g2_aux_exps,
+                        b_g2_aux_skip,
+                        b_g2_aux_n,
+                        &mut multiexp_kern,
+                    );
+                    info!("ZQ: inputs phase 6: {:?}", start.elapsed());
+
+                    inputs_tx.send((idx, (
+                        a_inputs,
+                        a_aux,
+                        b_g1_inputs,
+                        b_g1_aux,
+                        b_g2_inputs,
+                        b_g2_aux,
+                    ))).unwrap();
+                }
+            });
+        }
+        drop(inputs_tx);
+    });
 
-            let start = Instant::now();
-            let b_g2_inputs = multiexp(
-                &worker,
-                b_g2_inputs_source.clone(),
-                b_input_density.clone(),
-                input_assignment.clone(),
-                &mut multiexp_kern,
-            );
-            info!("ZQ: inputs phase 5: {:?}", start.elapsed());
-
-            let start = Instant::now();
-            let (
-                b_g2_aux_bss,
-                b_g2_aux_exps,
-                b_g2_aux_skip,
-                b_g2_aux_n
-            ) = density_filter(
-                b_g2_aux_source.clone(),
-                b_aux_density.clone(),
-                aux_assignment.clone()
-            );
-            let b_g2_aux = multiexp_skipdensity(
-                &worker,
-                b_g2_aux_bss,
-                b_g2_aux_exps,
-                b_g2_aux_skip,
-                b_g2_aux_n,
-                &mut multiexp_kern,
-            );
-            info!("ZQ: inputs phase 6: {:?}", start.elapsed());
-
-            Ok((
-                a_inputs,
-                a_aux,
-                b_g1_inputs,
-                b_g1_aux,
-                b_g2_inputs,
-                b_g2_aux,
-            ))
-        })
-        .collect::<Result<Vec<_>, SynthesisError>>()?;
+    let mut inputs: Vec<Option<_>> = (0..n_provers).map(|_| None).collect();
+    for (idx, result) in inputs_rx.iter() {
+        inputs[idx] = Some(result);
+    }
+    let inputs: Vec<_> = inputs.into_iter().map(|i| i.unwrap()).collect();
     info!("ZQ: inputs end: {:?}", now.elapsed());
 
-    drop(multiexp_kern);
     #[cfg(feature = "gpu")]
     drop(prio_lock);
 
@@ -929,6 +1162,393 @@ where
     Ok(proofs)
 }
 
+/// How many circuits `create_proof_batch_priority_streaming` keeps in flight
+/// at once (synthesizing, FFT-ing, and multiexp-ing simultaneously). Higher
+/// values overlap more of the three stages at the cost of holding that many
+/// circuits' coefficient/assignment buffers resident; `BELLMAN_STREAMING_DEPTH`
+/// overrides the default for callers who want to trade further.
+fn streaming_in_flight_depth() -> usize {
+    std::env::var("BELLMAN_STREAMING_DEPTH")
+        .ok()
+        .and_then(|v| v.parse::<usize>().ok())
+        .filter(|&depth| depth > 0)
+        .unwrap_or(3)
+}
+
+// A single circuit's output once it has made it through the FFT stage:
+// everything `create_proof_batch_priority_streaming`'s multiexp stage needs,
+// with the EvaluationDomain coefficients and ProvingAssignment already
+// dropped in favour of the flattened scalar reprs multiexp consumes.
+struct StreamedCircuit<E: Engine> {
+    h_a: Arc<Vec<<E::Fr as PrimeField>::Repr>>,
+    input_assignment: Arc<Vec<<E::Fr as PrimeField>::Repr>>,
+    aux_assignment: Arc<Vec<<E::Fr as PrimeField>::Repr>>,
+    b_input_density: Arc<DensityTracker>,
+    b_aux_density: Arc<DensityTracker>,
+    a_aux_density: DensityTracker,
+}
+
+pub fn create_random_proof_batch_priority_streaming<E, C, R, P: ParameterSource<E>>(
+    circuits: Vec<C>,
+    params: P,
+    rng: &mut R,
+    priority: bool,
+) -> Result<Vec<Proof<E>>, SynthesisError>
+where
+    E: Engine,
+    C: Circuit<E> + Send,
+    R: RngCore,
+{
+    let r_s = (0..circuits.len()).map(|_| E::Fr::random(rng)).collect();
+    let s_s = (0..circuits.len()).map(|_| E::Fr::random(rng)).collect();
+
+    create_proof_batch_priority_streaming::<E, C, P>(circuits, params, r_s, s_s, priority)
+}
+
+/// Low-memory variant of `create_proof_batch_priority`: instead of
+/// synthesizing every circuit in the batch before doing any FFTs, and doing
+/// every FFT before any multiexps, circuits are streamed through all three
+/// stages at once with a bounded in-flight depth (see
+/// `streaming_in_flight_depth`). Circuit `i+1` is being synthesized while
+/// circuit `i` is in its FFT stage and circuit `i-1` is in its multiexp
+/// stage, and each circuit's coefficient/assignment buffers are dropped as
+/// soon as its multiexps are scheduled rather than living until the whole
+/// batch finishes. Returns the same `Vec<Proof<E>>` as the eager path, just
+/// trading a little pipeline overlap for much lower peak memory.
+pub fn create_proof_batch_priority_streaming<E, C, P: ParameterSource<E>>(
+    circuits: Vec<C>,
+    params: P,
+    r_s: Vec<E::Fr>,
+    s_s: Vec<E::Fr>,
+    priority: bool,
+) -> Result<Vec<Proof<E>>, SynthesisError>
+where
+    E: Engine,
+    C: Circuit<E> + Send,
+{
+    info!("Bellperson {} is being used (streaming)!", BELLMAN_VERSION);
+
+    THREAD_POOL
+        .install(|| create_proof_batch_priority_streaming_inner(circuits, params, r_s, s_s, priority))
+}
+
+fn create_proof_batch_priority_streaming_inner<E, C, P: ParameterSource<E>>(
+    circuits: Vec<C>,
+    params: P,
+    r_s: Vec<E::Fr>,
+    s_s: Vec<E::Fr>,
+    priority: bool,
+) -> Result<Vec<Proof<E>>, SynthesisError>
+where
+    E: Engine,
+    C: Circuit<E> + Send,
+{
+    let start = Instant::now();
+    let worker = Worker::new();
+
+    let num_circuits = circuits.len();
+    let mut circuits_iter = circuits.into_iter();
+    let first_circuit = circuits_iter
+        .next()
+        .expect("at least one circuit is required");
+
+    // Circuit 0 is synthesized up front, off the pipeline, purely to learn
+    // the domain size and input length needed to size the FFT/multiexp
+    // kernels created once below and reused for the whole streamed batch.
+    let first_prover = synthesize_one::<E, C>(first_circuit)?;
+    let input_len = first_prover.input_assignment.len();
+    let vk = params.get_vk(input_len)?;
+    let n = first_prover.a.len();
+
+    let mut log_d = 0;
+    while (1 << log_d) < n {
+        log_d += 1;
+    }
+
+    info!("ZQ streaming: get params start");
+    let now = Instant::now();
+    let (tx_h, rx_h) = mpsc::channel();
+    let (tx_l, rx_l) = mpsc::channel();
+    let (tx_a, rx_a) = mpsc::channel();
+    let (tx_bg1, rx_bg1) = mpsc::channel();
+    let (tx_bg2, rx_bg2) = mpsc::channel();
+    let mut pool = Pool::new(5);
+    pool.scoped(|scoped| {
+        let params = &params;
+
+        scoped.execute(move || tx_h.send(params.get_h(0).unwrap()).unwrap());
+        scoped.execute(move || tx_l.send(params.get_l(0).unwrap()).unwrap());
+        scoped.execute(move || tx_a.send(params.get_a(input_len, 0).unwrap()).unwrap());
+        scoped.execute(move || tx_bg1.send(params.get_b_g1(1, 0).unwrap()).unwrap());
+        scoped.execute(move || tx_bg2.send(params.get_b_g2(1, 0).unwrap()).unwrap());
+    });
+    let h_params = rx_h.recv().unwrap();
+    let l_params = rx_l.recv().unwrap();
+    let (a_inputs_source, a_aux_source) = rx_a.recv().unwrap();
+    let (b_g1_inputs_source, b_g1_aux_source) = rx_bg1.recv().unwrap();
+    let (b_g2_inputs_source, b_g2_aux_source) = rx_bg2.recv().unwrap();
+    info!("ZQ streaming: get params end: {:?}", now.elapsed());
+
+    #[cfg(feature = "gpu")]
+    let prio_lock = if priority {
+        Some(PriorityLock::lock())
+    } else {
+        None
+    };
+
+    let depth = streaming_in_flight_depth();
+    info!("ZQ streaming: in-flight depth {}", depth);
+
+    let (synth_tx, synth_rx) = mpsc::sync_channel::<(usize, Result<ProvingAssignment<E>, SynthesisError>)>(depth);
+    let (fft_tx, fft_rx) = mpsc::sync_channel::<(usize, Result<StreamedCircuit<E>, SynthesisError>)>(depth);
+    let (proof_tx, proof_rx) = mpsc::channel::<(usize, Result<Proof<E>, SynthesisError>)>();
+
+    let mut pipeline_pool = Pool::new(3);
+    pipeline_pool.scoped(|scoped| {
+        // Stage 1: synthesize circuit i+1 while i is being FFT'd/multiexp'd.
+        scoped.execute(move || {
+            synth_tx.send((0, Ok(first_prover))).unwrap();
+            for (offset, circuit) in circuits_iter.enumerate() {
+                let idx = offset + 1;
+                synth_tx.send((idx, synthesize_one::<E, C>(circuit))).unwrap();
+            }
+        });
+
+        // Stage 2: iFFT/coset-FFT each circuit into its `h` coefficients,
+        // then drop its EvaluationDomains/ProvingAssignment in favour of the
+        // flattened reprs the multiexp stage needs.
+        let worker_fft = worker.clone();
+        scoped.execute(move || {
+            let mut fft_kern = Some(LockedFFTKernel::<E>::new(log_d, priority));
+            let mut fft_kern_1 = Some(LockedFFTKernel_1::<E>::new(log_d, priority));
+
+            for (idx, prover) in synth_rx.iter() {
+                let mut prover = match prover {
+                    Ok(prover) => prover,
+                    Err(e) => {
+                        fft_tx.send((idx, Err(e))).unwrap();
+                        continue;
+                    }
+                };
+
+                let result = (|| -> Result<StreamedCircuit<E>, SynthesisError> {
+                    assert_eq!(
+                        prover.a.len(),
+                        n,
+                        "only equally sized circuits are supported"
+                    );
+                    let mut a = EvaluationDomain::from_coeffs(std::mem::replace(&mut prover.a, Vec::new()))?;
+                    let mut b = EvaluationDomain::from_coeffs(std::mem::replace(&mut prover.b, Vec::new()))?;
+                    let mut c = EvaluationDomain::from_coeffs(std::mem::replace(&mut prover.c, Vec::new()))?;
+
+                    a.ifft(&worker_fft, &mut fft_kern)?;
+                    a.coset_fft(&worker_fft, &mut fft_kern)?;
+                    b.ifft_1(&worker_fft, &mut fft_kern_1)?;
+                    b.coset_fft_1(&worker_fft, &mut fft_kern_1)?;
+                    c.ifft(&worker_fft, &mut fft_kern)?;
+                    c.coset_fft(&worker_fft, &mut fft_kern)?;
+
+                    a.mul_assign(&worker_fft, &b);
+                    drop(b);
+                    a.sub_assign(&worker_fft, &c);
+                    drop(c);
+                    a.divide_by_z_on_coset(&worker_fft);
+                    a.icoset_fft(&worker_fft, &mut fft_kern)?;
+
+                    let mut h_a = a.into_coeffs();
+                    let h_len = h_a.len() - 1;
+                    h_a.truncate(h_len);
+                    let h_a = Arc::new(h_a.into_par_iter().map(|s| s.0.into_repr()).collect::<Vec<_>>());
+
+                    let input_assignment = Arc::new(
+                        std::mem::replace(&mut prover.input_assignment, Vec::new())
+                            .into_iter()
+                            .map(|s| s.into_repr())
+                            .collect::<Vec<_>>(),
+                    );
+                    let aux_assignment = Arc::new(
+                        std::mem::replace(&mut prover.aux_assignment, Vec::new())
+                            .into_iter()
+                            .map(|s| s.into_repr())
+                            .collect::<Vec<_>>(),
+                    );
+                    let b_input_density = Arc::new(std::mem::replace(&mut prover.b_input_density, DensityTracker::new()));
+                    let b_aux_density = Arc::new(std::mem::replace(&mut prover.b_aux_density, DensityTracker::new()));
+                    let a_aux_density = std::mem::replace(&mut prover.a_aux_density, DensityTracker::new());
+
+                    Ok(StreamedCircuit {
+                        h_a,
+                        input_assignment,
+                        aux_assignment,
+                        b_input_density,
+                        b_aux_density,
+                        a_aux_density,
+                    })
+                })();
+                // `prover` (and its now-empty a/b/c) is dropped here, before
+                // the next circuit's FFT even starts.
+                fft_tx.send((idx, result)).unwrap();
+            }
+        });
+
+        // Stage 3: h/l/input multiexps, then assemble the finished proof
+        // immediately so nothing from this circuit needs to stay alive for
+        // the rest of the batch.
+        let worker_mx = worker.clone();
+        scoped.execute(move || {
+            let mut multiexp_kern = Some(LockedMultiexpKernel::<E>::new(log_d, priority));
+
+            for (idx, item) in fft_rx.iter() {
+                let item = match item {
+                    Ok(item) => item,
+                    Err(e) => {
+                        proof_tx.send((idx, Err(e))).unwrap();
+                        continue;
+                    }
+                };
+
+                let result = (|| -> Result<Proof<E>, SynthesisError> {
+                    if vk.delta_g1.is_zero() || vk.delta_g2.is_zero() {
+                        return Err(SynthesisError::UnexpectedIdentity);
+                    }
+
+                    let h = multiexp_fulldensity(
+                        &worker_mx,
+                        h_params.clone(),
+                        FullDensity,
+                        item.h_a,
+                        &mut multiexp_kern,
+                    );
+                    let l = multiexp_fulldensity(
+                        &worker_mx,
+                        l_params.clone(),
+                        FullDensity,
+                        item.aux_assignment.clone(),
+                        &mut multiexp_kern,
+                    );
+
+                    let a_inputs = multiexp_fulldensity(
+                        &worker_mx,
+                        a_inputs_source.clone(),
+                        FullDensity,
+                        item.input_assignment.clone(),
+                        &mut multiexp_kern,
+                    );
+                    let (a_aux_bss, a_aux_exps, a_aux_skip, a_aux_n) = density_filter(
+                        a_aux_source.clone(),
+                        Arc::new(item.a_aux_density),
+                        item.aux_assignment.clone(),
+                    );
+                    let a_aux = multiexp_skipdensity(
+                        &worker_mx,
+                        a_aux_bss,
+                        a_aux_exps,
+                        a_aux_skip,
+                        a_aux_n,
+                        &mut multiexp_kern,
+                    );
+
+                    let b_g1_inputs = multiexp(
+                        &worker_mx,
+                        b_g1_inputs_source.clone(),
+                        item.b_input_density.clone(),
+                        item.input_assignment.clone(),
+                        &mut multiexp_kern,
+                    );
+                    let (b_g1_aux_bss, b_g1_aux_exps, b_g1_aux_skip, b_g1_aux_n) = density_filter(
+                        b_g1_aux_source.clone(),
+                        item.b_aux_density.clone(),
+                        item.aux_assignment.clone(),
+                    );
+                    let b_g1_aux = multiexp_skipdensity(
+                        &worker_mx,
+                        b_g1_aux_bss,
+                        b_g1_aux_exps,
+                        b_g1_aux_skip,
+                        b_g1_aux_n,
+                        &mut multiexp_kern,
+                    );
+
+                    let b_g2_inputs = multiexp(
+                        &worker_mx,
+                        b_g2_inputs_source.clone(),
+                        item.b_input_density.clone(),
+                        item.input_assignment.clone(),
+                        &mut multiexp_kern,
+                    );
+                    let (b_g2_aux_bss, b_g2_aux_exps, b_g2_aux_skip, b_g2_aux_n) = density_filter(
+                        b_g2_aux_source.clone(),
+                        item.b_aux_density.clone(),
+                        item.aux_assignment.clone(),
+                    );
+                    let b_g2_aux = multiexp_skipdensity(
+                        &worker_mx,
+                        b_g2_aux_bss,
+                        b_g2_aux_exps,
+                        b_g2_aux_skip,
+                        b_g2_aux_n,
+                        &mut multiexp_kern,
+                    );
+
+                    let r = r_s[idx];
+                    let s = s_s[idx];
+
+                    let mut g_a = vk.delta_g1.mul(r);
+                    g_a.add_assign_mixed(&vk.alpha_g1);
+                    let mut g_b = vk.delta_g2.mul(s);
+                    g_b.add_assign_mixed(&vk.beta_g2);
+                    let mut g_c;
+                    {
+                        let mut rs = r;
+                        rs.mul_assign(&s);
+
+                        g_c = vk.delta_g1.mul(rs);
+                        g_c.add_assign(&vk.alpha_g1.mul(s));
+                        g_c.add_assign(&vk.beta_g1.mul(r));
+                    }
+
+                    let mut a_answer = a_inputs.wait()?;
+                    a_answer.add_assign(&a_aux.wait()?);
+                    g_a.add_assign(&a_answer);
+                    a_answer.mul_assign(s);
+                    g_c.add_assign(&a_answer);
+
+                    let mut b1_answer = b_g1_inputs.wait()?;
+                    b1_answer.add_assign(&b_g1_aux.wait()?);
+                    let mut b2_answer = b_g2_inputs.wait()?;
+                    b2_answer.add_assign(&b_g2_aux.wait()?);
+
+                    g_b.add_assign(&b2_answer);
+                    b1_answer.mul_assign(r);
+                    g_c.add_assign(&b1_answer);
+                    g_c.add_assign(&h.wait()?);
+                    g_c.add_assign(&l.wait()?);
+
+                    Ok(Proof {
+                        a: g_a.into_affine(),
+                        b: g_b.into_affine(),
+                        c: g_c.into_affine(),
+                    })
+                })();
+
+                proof_tx.send((idx, result)).unwrap();
+            }
+        });
+    });
+
+    #[cfg(feature = "gpu")]
+    drop(prio_lock);
+
+    let mut proofs: Vec<Option<Result<Proof<E>, SynthesisError>>> = (0..num_circuits).map(|_| None).collect();
+    for (idx, result) in proof_rx.iter() {
+        proofs[idx] = Some(result);
+    }
+
+    info!("ZQ streaming: prover time: {:?}", start.elapsed());
+
+    proofs.into_iter().map(|p| p.expect("every circuit produces exactly one result")).collect()
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;